@@ -0,0 +1,9 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use pngme::png::Png;
+
+// Parsing arbitrary bytes must only ever return Ok or Err, never panic
+fuzz_target!(|data: &[u8]| {
+    let _ = Png::try_from(data);
+});
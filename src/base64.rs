@@ -0,0 +1,90 @@
+use anyhow::bail;
+use thiserror::Error;
+
+const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+#[derive(Error, Debug)]
+pub enum Base64Error {
+    #[error("Invalid base64 character '{0}' at position {1}")]
+    InvalidChar(char, usize),
+    #[error("Base64 input length must be a multiple of 4 once whitespace is removed, got {0}")]
+    InvalidLength(usize),
+    #[error("Base64 padding is only allowed at the end of the input")]
+    InvalidPadding,
+}
+
+/// Standard base64 with `=` padding
+pub fn encode(bytes: &[u8]) -> String {
+    let mut out = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for group in bytes.chunks(3) {
+        let n = group.iter().enumerate().fold(0u32, |n, (i, b)| n | (*b as u32) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= group.len() {
+                out.push(ALPHABET[(n >> (18 - 6 * i) & 0x3F) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+/// Decodes standard padded base64, ASCII whitespace such as line breaks is ignored
+pub fn decode(s: &str) -> anyhow::Result<Vec<u8>> {
+    let mut values = Vec::with_capacity(s.len());
+    let mut padding = 0;
+    for (pos, ch) in s.char_indices().filter(|(_, ch)| !ch.is_ascii_whitespace()) {
+        match ch {
+            '=' => padding += 1,
+            _ if padding > 0 => bail!(Base64Error::InvalidPadding),
+            _ => {
+                let value = ALPHABET.iter().position(|c| *c as char == ch).ok_or(Base64Error::InvalidChar(ch, pos))?;
+                values.push(value as u32);
+            }
+        }
+    }
+    let len = values.len() + padding;
+    if len % 4 != 0 {
+        bail!(Base64Error::InvalidLength(len));
+    }
+    if padding > 2 {
+        bail!(Base64Error::InvalidPadding);
+    }
+
+    let mut out = Vec::with_capacity(len / 4 * 3);
+    for group in values.chunks(4) {
+        let n = group.iter().enumerate().fold(0u32, |n, (i, v)| n | v << (18 - 6 * i));
+        let bytes = [(n >> 16) as u8, (n >> 8) as u8, n as u8];
+        out.extend_from_slice(&bytes[..group.len() - 1]);
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode() {
+        assert_eq!(encode(b""), "");
+        assert_eq!(encode(b"f"), "Zg==");
+        assert_eq!(encode(b"fo"), "Zm8=");
+        assert_eq!(encode(b"foobar"), "Zm9vYmFy");
+        assert_eq!(encode(&[0xFB, 0xFF]), "+/8=");
+    }
+
+    #[test]
+    fn test_decode() {
+        assert_eq!(decode("Zm9vYg==").unwrap(), b"foob");
+        assert_eq!(decode("Zm9v\nYmFy\n").unwrap(), b"foobar");
+        assert_eq!(decode("+/8=").unwrap(), [0xFB, 0xFF]);
+    }
+
+    #[test]
+    fn test_decode_invalid() {
+        assert!(decode("Zm9").is_err());
+        assert!(decode("Zm9v!A==").is_err());
+        assert!(decode("Zg==Zg==").is_err());
+        assert!(decode("Z===").is_err());
+    }
+}
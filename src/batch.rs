@@ -0,0 +1,91 @@
+use std::io::Write;
+
+use anyhow::bail;
+
+use crate::cli::ErrorFormat;
+use crate::report::{self, FileContext};
+
+/// Runs `job` on every file, a failure names its file and either stops the batch (`fail_fast`) or is reported on `err_out` and skipped
+pub fn run_all<W, F>(files: &[String], fail_fast: bool, error_format: ErrorFormat, err_out: &mut W, mut job: F) -> anyhow::Result<()>
+where
+    W: Write,
+    F: FnMut(&str) -> anyhow::Result<()>,
+{
+    let mut failed = 0;
+    for file in files {
+        if let Err(err) = job(file) {
+            if fail_fast {
+                return Err(err.context(FileContext(file.clone())));
+            }
+            match error_format {
+                ErrorFormat::Text => writeln!(err_out, "Error in {}: {:#}", file, err)?,
+                ErrorFormat::Json => writeln!(err_out, "{}", report::error_json(&err.context(FileContext(file.clone())), None))?,
+            }
+            failed += 1;
+        }
+    }
+    if failed > 0 {
+        bail!("{} of {} file(s) failed", failed, files.len());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn files() -> Vec<String> {
+        ["good.png", "bad.png", "also_good.png"].iter().map(|f| f.to_string()).collect()
+    }
+
+    fn job(visited: &mut Vec<String>) -> impl FnMut(&str) -> anyhow::Result<()> + '_ {
+        |file| {
+            visited.push(file.to_string());
+            if file == "bad.png" { bail!("broken") } else { Ok(()) }
+        }
+    }
+
+    #[test]
+    fn test_run_all_continues_by_default() {
+        let (mut visited, mut err_out) = (Vec::new(), Vec::new());
+        let err = run_all(&files(), false, ErrorFormat::Text, &mut err_out, job(&mut visited)).unwrap_err();
+
+        assert_eq!(visited, files());
+        assert_eq!(err.to_string(), "1 of 3 file(s) failed");
+        assert_eq!(String::from_utf8(err_out).unwrap(), "Error in bad.png: broken\n");
+    }
+
+    #[test]
+    fn test_run_all_fail_fast() {
+        let (mut visited, mut err_out) = (Vec::new(), Vec::new());
+        let err = run_all(&files(), true, ErrorFormat::Text, &mut err_out, job(&mut visited)).unwrap_err();
+
+        assert_eq!(visited, ["good.png", "bad.png"]);
+        assert_eq!(err.to_string(), "Failed on: bad.png");
+        assert_eq!(err.downcast_ref::<FileContext>().unwrap().0, "bad.png");
+        assert!(err_out.is_empty());
+    }
+
+    #[test]
+    fn test_run_all_json_names_each_failing_file() {
+        let files: Vec<String> = ["good.png", "bad.png", "also_bad.png"].iter().map(|f| f.to_string()).collect();
+        let mut err_out = Vec::new();
+        let err = run_all(&files, false, ErrorFormat::Json, &mut err_out, |file| {
+            if file.ends_with("bad.png") { bail!("broken") } else { Ok(()) }
+        }).unwrap_err();
+
+        assert_eq!(err.to_string(), "2 of 3 file(s) failed");
+        let err_out = String::from_utf8(err_out).unwrap();
+        let lines: Vec<&str> = err_out.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].contains("\"file\":\"bad.png\""), "{}", lines[0]);
+        assert!(lines[1].contains("\"file\":\"also_bad.png\""), "{}", lines[1]);
+        assert!(lines[1].contains("\"message\":\"Failed on: also_bad.png: broken\""), "{}", lines[1]);
+    }
+
+    #[test]
+    fn test_run_all_success() {
+        let files = vec!["good.png".to_string()];
+        assert!(run_all(&files, false, ErrorFormat::Text, &mut Vec::new(), job(&mut Vec::new())).is_ok());
+    }
+}
@@ -0,0 +1,56 @@
+use std::io::Write;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+use pngme::chunk::Chunk;
+use pngme::chunk_type::ChunkType;
+use pngme::png::Png;
+
+/// Times parsing, appending a chunk and serializing `bytes` over `iterations` runs and writes the throughput of each
+pub fn write_throughput<W: Write>(out: &mut W, bytes: &[u8], iterations: usize) -> anyhow::Result<()> {
+    let chunk_type = ChunkType::from_str("ruSt")?;
+    let (mut parse, mut append, mut serialize) = (Duration::ZERO, Duration::ZERO, Duration::ZERO);
+    for _ in 0..iterations {
+        let start = Instant::now();
+        let mut png = Png::try_from(bytes)?;
+        parse += start.elapsed();
+
+        let start = Instant::now();
+        png.append_chunk_indexed(Chunk::new(chunk_type.clone(), b"benchmark message".to_vec()));
+        append += start.elapsed();
+
+        let start = Instant::now();
+        std::hint::black_box(png.as_bytes());
+        serialize += start.elapsed();
+    }
+
+    let total_mb = (bytes.len() * iterations) as f64 / 1_000_000.0;
+    writeln!(out, "{} iterations over {} bytes", iterations, bytes.len())?;
+    for (name, elapsed) in [("parse", parse), ("append", append), ("serialize", serialize)] {
+        let seconds = elapsed.as_secs_f64().max(f64::EPSILON);
+        writeln!(out, "{:<9} {:>10.2} MB/s ({:?})", name, total_mb / seconds, elapsed)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DICE_PNG: &[u8] = include_bytes!("../fuzz/corpus/png_try_from/dice.png");
+
+    #[test]
+    fn test_write_throughput() {
+        let mut out = Vec::new();
+        write_throughput(&mut out, DICE_PNG, 3).unwrap();
+        let out = String::from_utf8(out).unwrap();
+
+        assert!(out.starts_with(&format!("3 iterations over {} bytes\n", DICE_PNG.len())));
+        for name in ["parse", "append", "serialize"] {
+            let line = out.lines().find(|line| line.starts_with(name)).unwrap();
+            let rate: f64 = line.split_whitespace().nth(1).unwrap().parse().unwrap();
+            assert!(rate > 0.0);
+            assert!(line.contains("MB/s"));
+        }
+    }
+}
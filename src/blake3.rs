@@ -0,0 +1,131 @@
+const IV: [u32; 8] = [
+    0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f, 0x9b05688c, 0x1f83d9ab, 0x5be0cd19,
+];
+
+const MSG_PERMUTATION: [usize; 16] = [2, 6, 3, 10, 7, 0, 4, 13, 1, 11, 12, 5, 9, 14, 15, 8];
+
+const CHUNK_LEN: usize = 1024;
+const BLOCK_LEN: usize = 64;
+
+const CHUNK_START: u32 = 1 << 0;
+const CHUNK_END: u32 = 1 << 1;
+const PARENT: u32 = 1 << 2;
+const ROOT: u32 = 1 << 3;
+
+/// BLAKE3 digest of `data` in the default 32-byte hash mode
+pub fn digest(data: &[u8]) -> [u8; 32] {
+    let mut out = [0u8; 32];
+    for (bytes, word) in out.chunks_mut(4).zip(subtree(data, 0, ROOT)) {
+        bytes.copy_from_slice(&word.to_le_bytes());
+    }
+    out
+}
+
+// Chaining value of the chunks in `data`, the left subtree always holds the largest power of two chunks
+// that leaves at least one for the right
+fn subtree(data: &[u8], counter: u64, root: u32) -> [u32; 8] {
+    if data.len() <= CHUNK_LEN {
+        return chunk(data, counter, root);
+    }
+    let chunks = data.len().div_ceil(CHUNK_LEN);
+    let left_chunks = 1 << (usize::BITS - 1 - (chunks - 1).leading_zeros());
+    let (left, right) = data.split_at(left_chunks * CHUNK_LEN);
+    let left = subtree(left, counter, 0);
+    let right = subtree(right, counter + left_chunks as u64, 0);
+
+    let mut block = [0u32; 16];
+    block[..8].copy_from_slice(&left);
+    block[8..].copy_from_slice(&right);
+    first_eight(compress(&IV, &block, 0, BLOCK_LEN as u32, PARENT | root))
+}
+
+fn chunk(data: &[u8], counter: u64, root: u32) -> [u32; 8] {
+    // An empty input is still a single empty block
+    let blocks: Vec<&[u8]> = if data.is_empty() { vec![data] } else { data.chunks(BLOCK_LEN).collect() };
+    let last = blocks.len() - 1;
+    let mut cv = IV;
+    for (idx, bytes) in blocks.into_iter().enumerate() {
+        let mut padded = [0u8; BLOCK_LEN];
+        padded[..bytes.len()].copy_from_slice(bytes);
+        let mut block = [0u32; 16];
+        for (word, bytes) in block.iter_mut().zip(padded.chunks(4)) {
+            *word = u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+        }
+
+        let mut flags = if idx == 0 { CHUNK_START } else { 0 };
+        if idx == last {
+            flags |= CHUNK_END | root;
+        }
+        cv = first_eight(compress(&cv, &block, counter, bytes.len() as u32, flags));
+    }
+    cv
+}
+
+fn first_eight(words: [u32; 16]) -> [u32; 8] {
+    let mut out = [0u32; 8];
+    out.copy_from_slice(&words[..8]);
+    out
+}
+
+fn compress(cv: &[u32; 8], block: &[u32; 16], counter: u64, block_len: u32, flags: u32) -> [u32; 16] {
+    let mut state = [
+        cv[0], cv[1], cv[2], cv[3], cv[4], cv[5], cv[6], cv[7],
+        IV[0], IV[1], IV[2], IV[3], counter as u32, (counter >> 32) as u32, block_len, flags,
+    ];
+    let mut m = *block;
+    for round in 0..7 {
+        g(&mut state, 0, 4, 8, 12, m[0], m[1]);
+        g(&mut state, 1, 5, 9, 13, m[2], m[3]);
+        g(&mut state, 2, 6, 10, 14, m[4], m[5]);
+        g(&mut state, 3, 7, 11, 15, m[6], m[7]);
+        g(&mut state, 0, 5, 10, 15, m[8], m[9]);
+        g(&mut state, 1, 6, 11, 12, m[10], m[11]);
+        g(&mut state, 2, 7, 8, 13, m[12], m[13]);
+        g(&mut state, 3, 4, 9, 14, m[14], m[15]);
+        if round < 6 {
+            m = MSG_PERMUTATION.map(|idx| m[idx]);
+        }
+    }
+    for i in 0..8 {
+        state[i] ^= state[i + 8];
+        state[i + 8] ^= cv[i];
+    }
+    state
+}
+
+fn g(state: &mut [u32; 16], a: usize, b: usize, c: usize, d: usize, mx: u32, my: u32) {
+    state[a] = state[a].wrapping_add(state[b]).wrapping_add(mx);
+    state[d] = (state[d] ^ state[a]).rotate_right(16);
+    state[c] = state[c].wrapping_add(state[d]);
+    state[b] = (state[b] ^ state[c]).rotate_right(12);
+    state[a] = state[a].wrapping_add(state[b]).wrapping_add(my);
+    state[d] = (state[d] ^ state[a]).rotate_right(8);
+    state[c] = state[c].wrapping_add(state[d]);
+    state[b] = (state[b] ^ state[c]).rotate_right(7);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hex;
+
+    // Inputs of the official test vectors repeat the bytes 0 to 250
+    fn input(len: usize) -> Vec<u8> {
+        (0..len).map(|i| (i % 251) as u8).collect()
+    }
+
+    #[test]
+    fn test_known_digests() {
+        assert_eq!(hex::encode(&digest(b"")), "af1349b9f5f9a1a6a0404dea36dcc9499bcb25c9adc112b7cc9a93cae41f3262");
+        assert_eq!(hex::encode(&digest(b"abc")), "6437b3ac38465133ffb63b75273a8db548c558465d79db03fd359c6cd5bd9d85");
+        assert_eq!(hex::encode(&digest(&input(1))), "2d3adedff11b61f14c886e35afa036736dcd87a74d27b5c1510225d0f592e213");
+    }
+
+    #[test]
+    fn test_known_tree_digests() {
+        // One full chunk, then the first parent nodes
+        assert_eq!(hex::encode(&digest(&input(1024))), "42214739f095a406f3fc83deb889744ac00df831c10daa55189b5d121c855af7");
+        assert_eq!(hex::encode(&digest(&input(1025))), "d00278ae47eb27b34faecf67b4fe263f82d5412916c1ffd97c8cb7fb814b8444");
+        assert_eq!(hex::encode(&digest(&input(2048))), "e776b6028c7cd22a4d0ba182a8bf62205d2ef576467e838ed6f2529b85fba24a");
+    }
+}
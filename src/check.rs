@@ -0,0 +1,299 @@
+use std::io::{self, Write};
+
+use pngme::chunk::Chunk;
+use pngme::png::{Png, Severity};
+
+/// Writes the one-screen health summary printed by the Check command
+pub fn write_summary<W: Write>(out: &mut W, png: &Png) -> io::Result<()> {
+    let yes_no = |b: bool| if b { "yes" } else { "no" };
+    let type_of = |chunk: Option<&Chunk>| chunk.map_or("-".to_string(), |chunk| chunk.chunk_type().to_string());
+    let crc_passed = png.chunks().iter().filter(|chunk| chunk.has_valid_crc()).count();
+    let problems = png.validate();
+
+    writeln!(out, "Valid signature: {}", yes_no(png.has_valid_signature()))?;
+    writeln!(out, "Chunks: {}", png.chunks().len())?;
+    writeln!(out, "First chunk: {}", type_of(png.first_chunk()))?;
+    writeln!(out, "Last chunk: {}", type_of(png.last_chunk()))?;
+    writeln!(out, "Valid structure: {}", yes_no(problems.is_empty()))?;
+    writeln!(out, "CRCs: {} passed, {} failed", crc_passed, png.chunks().len() - crc_passed)?;
+    // An invalid hIST chunk is already listed with the problems
+    if let Ok(Some(histogram)) = png.histogram() {
+        writeln!(out, "Histogram: {} palette entries", histogram.len())?;
+    }
+    problems.iter().try_for_each(|problem| writeln!(out, "  {}", problem))
+}
+
+/// Writes how many payload bytes each embedding mode can store, for the Capacity command
+pub fn write_capacity<W: Write>(out: &mut W, png: &Png) -> io::Result<()> {
+    let (critical, ancillary) = png.count_bytes_by_kind();
+    writeln!(out, "Carrier: {} bytes in critical and {} in ancillary chunks", critical, ancillary)?;
+    writeln!(out, "chunk: {} bytes per chunk, unlimited when split by encode-file", Png::MAX_PIECE_SIZE)?;
+    writeln!(out, "after-iend: unlimited, but dropped by most editors")?;
+    #[cfg(feature = "compression")]
+    match pngme::stego::capacity(png) {
+        Ok(capacity) => writeln!(out, "lsb: {} bytes", capacity)?,
+        Err(err) => writeln!(out, "lsb: unavailable, {}", err)?,
+    }
+    Ok(())
+}
+
+/// Writes every spec violation with its byte offset and severity for the Validate command, returning how many are errors
+pub fn write_validation<W: Write>(out: &mut W, png: &Png) -> io::Result<usize> {
+    let violations = png.violations();
+    for violation in &violations {
+        let offset = violation.offset.map_or("-".to_string(), |offset| offset.to_string());
+        writeln!(out, "{:>10} {}: {}", offset, violation.severity, violation.message)?;
+    }
+    let errors = violations.iter().filter(|violation| violation.severity == Severity::Error).count();
+    writeln!(out, "{} error(s), {} warning(s)", errors, violations.len() - errors)?;
+    Ok(errors)
+}
+
+// Flag combinations that are legal but rarely intended, or outright invalid
+fn unusual_flags(chunk: &Chunk) -> Vec<&'static str> {
+    let chunk_type = chunk.chunk_type();
+    let mut unusual = Vec::new();
+    if chunk_type.is_critical() && chunk_type.is_safe_to_copy() {
+        unusual.push("critical chunk marked safe to copy");
+    }
+    if !chunk_type.is_reserved_bit_valid() {
+        unusual.push("reserved bit is set");
+    }
+    unusual
+}
+
+/// Writes the four casing flags of every chunk for the AuditFlags command, marking unusual combinations
+pub fn write_flag_audit<W: Write>(out: &mut W, png: &Png) -> io::Result<()> {
+    let mut unusual_count = 0;
+    for (idx, chunk) in png.chunks().iter().enumerate() {
+        let chunk_type = chunk.chunk_type();
+        write!(out, "{} {}: {}, {}, {}, {}", idx, chunk_type,
+               if chunk_type.is_critical() { "critical" } else { "ancillary" },
+               if chunk_type.is_public() { "public" } else { "private" },
+               if chunk_type.is_reserved_bit_valid() { "reserved ok" } else { "reserved set" },
+               if chunk_type.is_safe_to_copy() { "safe to copy" } else { "unsafe to copy" })?;
+        let unusual = unusual_flags(chunk);
+        if unusual.is_empty() {
+            writeln!(out)?;
+        } else {
+            unusual_count += 1;
+            writeln!(out, "  <- unusual: {}", unusual.join(", "))?;
+        }
+    }
+    writeln!(out, "{} chunk(s) with unusual flags", unusual_count)
+}
+
+/// Writes the chunks a viewer or editor following the policy would drop, for the WhatIfStrip command.
+/// `drop_unsafe_unknown` drops ancillary chunks of unknown type that are not safe to copy, as editors must after changing the image,
+/// `drop_all_ancillary` drops every ancillary chunk
+pub fn write_strip_preview<W: Write>(out: &mut W, png: &Png, drop_unsafe_unknown: bool, drop_all_ancillary: bool) -> io::Result<()> {
+    let mut dropped = 0;
+    for (idx, chunk) in png.chunks().iter().enumerate() {
+        let chunk_type = chunk.chunk_type();
+        let unsafe_unknown = !chunk_type.is_safe_to_copy() && chunk_type.description().is_none();
+        if !chunk_type.is_critical() && (drop_all_ancillary || (drop_unsafe_unknown && unsafe_unknown)) {
+            dropped += 1;
+            writeln!(out, "{} {} ({} bytes)", idx, chunk_type, chunk.length())?;
+        }
+    }
+    writeln!(out, "{} chunk(s) would be dropped, the file was not changed", dropped)
+}
+
+// Bits per byte above which data looks compressed or encrypted, plain text and pixel data rarely get close
+const HIGH_ENTROPY: f64 = 7.5;
+// Shorter data cannot reach a high entropy, its byte counts are too sparse
+const MIN_ENTROPY_BYTES: usize = 256;
+// Ancillary chunks above this size are unusual whatever their type
+const LARGE_ANCILLARY_BYTES: u32 = 64 * 1024;
+// Standard ancillary types whose data is compressed, so a high entropy is expected
+const COMPRESSED_TYPES: [&[u8; 4]; 3] = [b"zTXt", b"iTXt", b"iCCP"];
+
+// Shannon entropy of `data` in bits per byte
+fn entropy(data: &[u8]) -> f64 {
+    let mut counts = [0usize; 256];
+    data.iter().for_each(|byte| counts[*byte as usize] += 1);
+    counts.iter().filter(|count| **count != 0).map(|count| {
+        let p = *count as f64 / data.len() as f64;
+        -p * p.log2()
+    }).sum()
+}
+
+// Reasons a chunk may hold embedded content, empty when it looks ordinary
+fn scan_findings(chunk: &Chunk, image_bytes: u64) -> Vec<String> {
+    let chunk_type = chunk.chunk_type();
+    let standard = chunk_type.description().is_some();
+    let mut findings = Vec::new();
+    if !standard {
+        findings.push(format!("non-standard {} type", if chunk_type.is_public() { "public" } else { "private" }));
+    }
+    if chunk_type.is_critical() && standard {
+        return findings;
+    }
+    let compressed = COMPRESSED_TYPES.iter().any(|name| *chunk_type == **name);
+    if !compressed && chunk.data().len() >= MIN_ENTROPY_BYTES {
+        let entropy = entropy(chunk.data());
+        if entropy > HIGH_ENTROPY {
+            findings.push(format!("high entropy ({:.2} bits/byte)", entropy));
+        }
+    }
+    if !chunk_type.is_critical() && (chunk.length() > LARGE_ANCILLARY_BYTES || chunk.length() as u64 > image_bytes) {
+        findings.push("unusually large for an ancillary chunk".to_string());
+    }
+    findings
+}
+
+/// Writes the chunks and trailing bytes that may hold embedded content, for the Scan command
+pub fn write_scan<W: Write>(out: &mut W, png: &Png) -> io::Result<()> {
+    let image_bytes = png.bytes_len_of_type("IDAT");
+    let mut flagged = 0;
+    for (idx, chunk) in png.chunks().iter().enumerate() {
+        let findings = scan_findings(chunk, image_bytes);
+        if !findings.is_empty() {
+            flagged += 1;
+            writeln!(out, "{} {} ({} bytes): {}", idx, chunk.chunk_type(), chunk.length(), findings.join(", "))?;
+        }
+    }
+    let trailing = png.trailing_bytes();
+    if !trailing.is_empty() {
+        flagged += 1;
+        writeln!(out, "{} bytes after IEND", trailing.len())?;
+    }
+    if flagged == 0 {
+        return writeln!(out, "Nothing suspicious found");
+    }
+    writeln!(out, "{} suspicious finding(s)", flagged)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use pngme::chunk_type::ChunkType;
+
+    use super::*;
+
+    const DICE_PNG: &[u8] = include_bytes!("../fuzz/corpus/png_try_from/dice.png");
+
+    #[test]
+    fn test_summary_of_good_png() {
+        let png = Png::try_from(DICE_PNG).unwrap();
+        let mut out = Vec::new();
+        write_summary(&mut out, &png).unwrap();
+
+        assert_eq!(String::from_utf8(out).unwrap(), format!(
+            "Valid signature: yes\nChunks: {}\nFirst chunk: IHDR\nLast chunk: IEND\nValid structure: yes\nCRCs: {} passed, 0 failed\n",
+            png.chunks().len(),
+            png.chunks().len(),
+        ));
+    }
+
+    #[test]
+    #[cfg(feature = "compression")]
+    fn test_capacity_per_mode() {
+        let mut png = Png::try_from(DICE_PNG).unwrap();
+        let mut out = Vec::new();
+        write_capacity(&mut out, &png).unwrap();
+        // The fixture is 50x50 RGBA, one bit per sample minus the 4 byte length prefix
+        assert!(String::from_utf8(out).unwrap().ends_with("lsb: 1246 bytes\n"));
+
+        png.replace_data_of_type("IHDR", vec![0, 0, 0, 50, 0, 0, 0, 50, 16, 6, 0, 0, 0]).unwrap();
+        let mut out = Vec::new();
+        write_capacity(&mut out, &png).unwrap();
+        assert!(String::from_utf8(out).unwrap().contains("lsb: unavailable, LSB mode needs an 8-bit"));
+    }
+
+    #[test]
+    fn test_scan_flags_hidden_content() {
+        let mut png = Png::try_from(DICE_PNG).unwrap();
+        let noise: Vec<u8> = (0..8192u32).map(|i| (i.wrapping_mul(2_654_435_761) >> 13) as u8).collect();
+        png.append_chunk_indexed(Chunk::new(ChunkType::from_str("hiDe").unwrap(), noise));
+        png.append_chunk_indexed(Chunk::new(ChunkType::from_str("tEXt").unwrap(), b"Comment\0short".to_vec()));
+        png.set_trailing_bytes(b"appended".to_vec());
+        let mut out = Vec::new();
+        write_scan(&mut out, &png).unwrap();
+        let out = String::from_utf8(out).unwrap();
+
+        // The fixture's RuSt chunk is private but holds a short message
+        assert!(out.contains("5 RuSt (3 bytes): non-standard private type\n"));
+        let hidden = out.lines().find(|line| line.contains("hiDe")).unwrap();
+        assert!(hidden.contains("non-standard private type, high entropy"));
+        assert!(hidden.ends_with("unusually large for an ancillary chunk"));
+        assert!(!out.contains("tEXt"));
+        assert!(out.ends_with("8 bytes after IEND\n3 suspicious finding(s)\n"));
+    }
+
+    #[test]
+    fn test_validation_lists_offsets_and_severities() {
+        let mut png = Png::try_from(DICE_PNG).unwrap();
+        assert_eq!(write_validation(&mut Vec::new(), &png).unwrap(), 0);
+
+        png.remove_chunk("IEND").unwrap();
+        png.set_trailing_bytes(b"appended".to_vec());
+        let mut out = Vec::new();
+        assert_eq!(write_validation(&mut out, &png).unwrap(), 1);
+        let rust_offset = png.byte_offset_of(png.chunks().len() - 1).unwrap();
+        let end = png.as_bytes().len() - 8;
+        assert_eq!(String::from_utf8(out).unwrap(), format!(
+            "{:>10} error: Last chunk must be IEND\n{:>10} warning: 8 bytes of trailing data after the last chunk\n1 error(s), 1 warning(s)\n",
+            rust_offset, end,
+        ));
+    }
+
+    #[test]
+    fn test_scan_of_plain_png() {
+        let mut png = Png::try_from(DICE_PNG).unwrap();
+        png.remove_chunk("RuSt").unwrap();
+        let mut out = Vec::new();
+        write_scan(&mut out, &png).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), "Nothing suspicious found\n");
+        assert!(entropy(&[7; 300]) == 0.0);
+    }
+
+    #[test]
+    fn test_flag_audit_marks_critical_safe_to_copy() {
+        let png = Png::try_from(DICE_PNG).unwrap();
+        let mut out = Vec::new();
+        write_flag_audit(&mut out, &png).unwrap();
+        let out = String::from_utf8(out).unwrap();
+
+        // The fixture's RuSt chunk is critical but safe to copy
+        let line = out.lines().find(|line| line.contains("RuSt")).unwrap();
+        assert!(line.ends_with("critical, private, reserved ok, safe to copy  <- unusual: critical chunk marked safe to copy"));
+        assert!(out.lines().find(|line| line.contains("IHDR")).unwrap().ends_with("critical, public, reserved ok, unsafe to copy"));
+        assert!(out.ends_with("1 chunk(s) with unusual flags\n"));
+    }
+
+    #[test]
+    fn test_summary_reports_crc_failures() {
+        let mut bytes = DICE_PNG.to_vec();
+        // Corrupt the CRC of IHDR, which ends at 8 + 12 + 13
+        bytes[32] ^= 0xFF;
+        let png = Png::from_bytes_lenient(&bytes).unwrap();
+        let mut out = Vec::new();
+        write_summary(&mut out, &png).unwrap();
+        let out = String::from_utf8(out).unwrap();
+
+        assert!(out.contains("Valid structure: no\n"));
+        assert!(out.contains(&format!("CRCs: {} passed, 1 failed\n", png.chunks().len() - 1)));
+        assert!(out.contains("  Chunk 0 (IHDR) has CRC"));
+    }
+
+    fn strip_preview(drop_unsafe_unknown: bool, drop_all_ancillary: bool) -> String {
+        let mut png = Png::try_from(DICE_PNG).unwrap();
+        png.append_chunk_indexed(Chunk::new(ChunkType::from_str("zkPT").unwrap(), b"unsafe".to_vec()));
+        png.append_chunk_indexed(Chunk::new(ChunkType::from_str("zkPt").unwrap(), b"safe".to_vec()));
+        let mut out = Vec::new();
+        write_strip_preview(&mut out, &png, drop_unsafe_unknown, drop_all_ancillary).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn test_strip_preview_unsafe_unknown() {
+        // sRGB and gAMA are unsafe to copy too, but known to every editor
+        assert_eq!(strip_preview(true, false), "6 zkPT (6 bytes)\n1 chunk(s) would be dropped, the file was not changed\n");
+        assert_eq!(
+            strip_preview(false, true),
+            "1 sRGB (1 bytes)\n2 gAMA (4 bytes)\n3 pHYs (9 bytes)\n6 zkPT (6 bytes)\n7 zkPt (4 bytes)\n5 chunk(s) would be dropped, the file was not changed\n"
+        );
+    }
+}
@@ -1,4 +1,5 @@
 use std::fmt::{Display, Formatter};
+use std::str::FromStr;
 
 use anyhow::bail;
 use crc::{Crc, CRC_32_ISO_HDLC};
@@ -14,33 +15,139 @@ pub enum ChunkError {
     InvalidChunkTypeLength,
     #[error("Length must not be greater than 2^31, {0} was provided")]
     LengthOverflow(u32),
-    #[error("Data bytes do not match the specified length")]
-    MismatchDataLength,
+    #[error("Chunk with the specified length must be {0} bytes in total, got {1}")]
+    MismatchDataLength(usize, usize),
     #[error("Expected 4 bytes for crc length")]
     InvalidCrcLength,
     #[error("Mismatch between provided crc ({0}), and expected crc ({1})")]
     CrcMismatch(u32, u32),
+    #[error("Invalid chunk type {}", escape_type_bytes(.0))]
+    InvalidChunkType([u8; 4]),
+    #[error("Text keyword must be 1 to 79 printable Latin-1 characters without leading, trailing or repeated spaces, got {0:?}")]
+    InvalidTextKeyword(String),
+    #[error("Text can only contain Latin-1 characters, got {0:?}")]
+    NonLatin1Text(char),
+    #[error("Unsupported text compression method {0}")]
+    UnsupportedCompressionMethod(u8),
+    #[error("Language tag must be ASCII letters, digits and hyphens, got {0:?}")]
+    InvalidLanguageTag(String),
+    #[error("Compressed text needs pngme built with the compression feature")]
+    CompressionUnavailable,
 }
 
+// Renders raw type bytes without assuming they form a valid ChunkType
+fn escape_type_bytes(bytes: &[u8; 4]) -> String {
+    bytes.iter()
+        .map(|b| if b.is_ascii_alphabetic() { (*b as char).to_string() } else { format!("\\x{:02x}", b) })
+        .collect()
+}
+
+impl ChunkError {
+    /// Stable, machine-readable name of the error variant
+    pub fn kind(&self) -> &'static str {
+        match self {
+            ChunkError::InvalidLength => "InvalidLength",
+            ChunkError::InvalidChunkTypeLength => "InvalidChunkTypeLength",
+            ChunkError::LengthOverflow(_) => "LengthOverflow",
+            ChunkError::MismatchDataLength(..) => "MismatchDataLength",
+            ChunkError::InvalidCrcLength => "InvalidCrcLength",
+            ChunkError::CrcMismatch(..) => "CrcMismatch",
+            ChunkError::InvalidChunkType(_) => "InvalidChunkType",
+            ChunkError::InvalidTextKeyword(_) => "InvalidTextKeyword",
+            ChunkError::NonLatin1Text(_) => "NonLatin1Text",
+            ChunkError::UnsupportedCompressionMethod(_) => "UnsupportedCompressionMethod",
+            ChunkError::InvalidLanguageTag(_) => "InvalidLanguageTag",
+            ChunkError::CompressionUnavailable => "CompressionUnavailable",
+        }
+    }
+}
+
+/// Fields of an iTXt chunk, which unlike tEXt and zTXt holds UTF-8 text
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct InternationalText {
+    pub keyword: String,
+    /// RFC 3066 language tag such as `en-GB`, empty when unknown
+    pub language: String,
+    /// The keyword translated into `language`
+    pub translated_keyword: String,
+    pub text: String,
+    pub compressed: bool,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
 pub struct Chunk {
     length: u32,
     data: Vec<u8>,
     chunk_type: ChunkType,
+    // CRC as read from the file, or computed on construction
+    stored_crc: u32,
+    // Set by lenient parsing when the input ended before the declared data length
+    truncated: bool,
 }
 
 impl Chunk {
     pub fn new(chunk_type: ChunkType, data: Vec<u8>) -> Self {
-        Chunk {
+        let mut chunk = Chunk {
             chunk_type,
             length: data.len() as u32,
             data,
+            stored_crc: 0,
+            truncated: false,
+        };
+        chunk.stored_crc = chunk.crc();
+        chunk
+    }
+
+    /// Builds a tEXt chunk: the keyword, a null separator and the Latin-1 encoded text
+    pub fn new_text(keyword: &str, text: &str) -> anyhow::Result<Chunk> {
+        let mut data = keyword_bytes(keyword)?;
+        data.extend(latin1_bytes(text)?);
+        Ok(Chunk::new(ChunkType::from_str("tEXt")?, data))
+    }
+
+    /// Builds a zTXt chunk: the keyword, a null separator, compression method 0 and the zlib compressed text
+    #[cfg(feature = "compression")]
+    pub fn new_ztxt(keyword: &str, text: &str) -> anyhow::Result<Chunk> {
+        let mut data = keyword_bytes(keyword)?;
+        data.push(0);
+        data.extend(crate::zlib::compress(&latin1_bytes(text)?));
+        Ok(Chunk::new(ChunkType::from_str("zTXt")?, data))
+    }
+
+    /// Builds an iTXt chunk: the keyword, the compression flag and method, the null-terminated language tag
+    /// and translated keyword, then the UTF-8 text, zlib compressed when `compressed` is set
+    pub fn new_itxt(entry: &InternationalText) -> anyhow::Result<Chunk> {
+        let valid_tag = entry.language.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'-');
+        if !valid_tag {
+            bail!(ChunkError::InvalidLanguageTag(entry.language.clone()));
         }
+        let mut data = keyword_bytes(&entry.keyword)?;
+        data.extend([entry.compressed as u8, 0]);
+        data.extend(entry.language.bytes().chain([0]));
+        data.extend(entry.translated_keyword.bytes().chain([0]));
+        if entry.compressed {
+            data.extend(deflate_text(entry.text.as_bytes())?);
+        } else {
+            data.extend(entry.text.as_bytes());
+        }
+        Ok(Chunk::new(ChunkType::from_str("iTXt")?, data))
+    }
+
+    /// Parses a chunk like `try_from`, but keeps chunks whose stored CRC doesn't match their content
+    /// and recovers the available data of chunks cut short before their declared length
+    pub fn from_bytes_lenient(value: &[u8]) -> anyhow::Result<Self> {
+        Chunk::parse(value, false)
     }
 
     pub fn length(&self) -> u32 {
         self.length
     }
 
+    /// Serialized size: the length, type and CRC fields plus the data
+    pub fn total_size(&self) -> usize {
+        self.length as usize + 12
+    }
+
     pub fn chunk_type(&self) -> &ChunkType {
         &self.chunk_type
     }
@@ -49,15 +156,95 @@ impl Chunk {
         &self.data
     }
 
+    pub fn into_data(self) -> Vec<u8> {
+        self.data
+    }
+
     pub fn crc(&self) -> u32 {
-        let bytes: Vec<u8> = self.chunk_type
-            .bytes()
-            .iter()
-            .chain(self.data.iter())
-            .copied()
-            .collect();
+        Chunk::compute_crc(&self.chunk_type, &self.data)
+    }
+
+    /// CRC over the type and data bytes, for assembling raw chunk bytes without building a `Chunk`
+    pub fn compute_crc(chunk_type: &ChunkType, data: &[u8]) -> u32 {
         let crc_alg = Crc::<u32>::new(&CRC_32_ISO_HDLC);
-        crc_alg.checksum(&bytes)
+        let mut digest = crc_alg.digest();
+        digest.update(&chunk_type.bytes());
+        digest.update(data);
+        digest.finalize()
+    }
+
+    pub fn stored_crc(&self) -> u32 {
+        self.stored_crc
+    }
+
+    /// Whether the input ended before the declared data length, only lenient parsing yields such chunks
+    pub fn is_truncated(&self) -> bool {
+        self.truncated
+    }
+
+    pub fn has_valid_crc(&self) -> bool {
+        self.stored_crc == self.crc()
+    }
+
+    /// Replaces the stored CRC with the one computed from the chunk content
+    pub fn recompute_crc(&mut self) {
+        self.stored_crc = self.crc();
+    }
+
+    /// Keyword and text of a tEXt chunk, `None` for other chunk types or a missing separator
+    pub fn text_entry(&self) -> Option<(String, String)> {
+        if self.chunk_type != *b"tEXt" {
+            return None;
+        }
+        let (keyword, text) = self.split_keyword()?;
+        Some((keyword, latin1_string(text)))
+    }
+
+    /// Keyword and decompressed text of a zTXt chunk, `None` for other chunk types or a missing separator
+    #[cfg(feature = "compression")]
+    pub fn ztxt_entry(&self) -> anyhow::Result<Option<(String, String)>> {
+        if self.chunk_type != *b"zTXt" {
+            return Ok(None);
+        }
+        let Some((keyword, rest)) = self.split_keyword() else {
+            return Ok(None);
+        };
+        match rest.split_first() {
+            Some((0, compressed)) => Ok(Some((keyword, latin1_string(&crate::zlib::decompress(compressed)?)))),
+            Some((method, _)) => bail!(ChunkError::UnsupportedCompressionMethod(*method)),
+            None => Ok(None),
+        }
+    }
+
+    /// Fields of an iTXt chunk with its text decompressed, `None` for other chunk types or missing separators
+    pub fn itxt_entry(&self) -> anyhow::Result<Option<InternationalText>> {
+        if self.chunk_type != *b"iTXt" {
+            return Ok(None);
+        }
+        let Some((keyword, [flag, method, rest @ ..])) = self.split_keyword() else {
+            return Ok(None);
+        };
+        let mut fields = rest.splitn(3, |b| *b == 0);
+        let (Some(language), Some(translated_keyword), Some(text)) = (fields.next(), fields.next(), fields.next()) else {
+            return Ok(None);
+        };
+        let text = match (flag, method) {
+            (0, _) => text.to_vec(),
+            (1, 0) => inflate_text(text)?,
+            (_, method) => bail!(ChunkError::UnsupportedCompressionMethod(*method)),
+        };
+        Ok(Some(InternationalText {
+            keyword,
+            language: String::from_utf8(language.to_vec())?,
+            translated_keyword: String::from_utf8(translated_keyword.to_vec())?,
+            text: String::from_utf8(text)?,
+            compressed: *flag == 1,
+        }))
+    }
+
+    fn split_keyword(&self) -> Option<(String, &[u8])> {
+        let separator = self.data.iter().position(|b| *b == 0)?;
+        Some((latin1_string(&self.data[..separator]), &self.data[separator + 1..]))
     }
 
     pub fn data_as_string(&self) -> anyhow::Result<String> {
@@ -65,22 +252,94 @@ impl Chunk {
         Ok(s.to_string())
     }
 
+    /// Data as UTF-8 with invalid sequences replaced by U+FFFD
+    pub fn data_as_string_lossy(&self) -> String {
+        String::from_utf8_lossy(&self.data).into_owned()
+    }
+
     pub fn as_bytes(&self) -> Vec<u8> {
         self.length.
             to_be_bytes()
             .iter()
             .chain(self.chunk_type.bytes().iter())
             .chain(self.data.iter())
-            .chain(self.crc().to_be_bytes().iter())
+            .chain(self.stored_crc.to_be_bytes().iter())
             .copied()
             .collect()
     }
+
+    pub fn bytes_iter(&self) -> impl Iterator<Item = u8> + '_ {
+        self.length
+            .to_be_bytes()
+            .into_iter()
+            .chain(self.chunk_type.bytes())
+            .chain(self.data.iter().copied())
+            .chain(self.stored_crc.to_be_bytes())
+    }
+}
+
+// Validated keyword followed by its null separator
+fn keyword_bytes(keyword: &str) -> anyhow::Result<Vec<u8>> {
+    let invalid = || ChunkError::InvalidTextKeyword(keyword.to_string());
+    let mut bytes = latin1_bytes(keyword).map_err(|_| invalid())?;
+    let printable = bytes.iter().all(|b| matches!(b, 32..=126 | 161..=255));
+    if bytes.is_empty() || bytes.len() > 79 || !printable
+        || keyword.starts_with(' ') || keyword.ends_with(' ') || keyword.contains("  ") {
+        bail!(invalid());
+    }
+    bytes.push(0);
+    Ok(bytes)
+}
+
+#[cfg(feature = "compression")]
+fn deflate_text(text: &[u8]) -> anyhow::Result<Vec<u8>> {
+    Ok(crate::zlib::compress(text))
+}
+
+#[cfg(not(feature = "compression"))]
+fn deflate_text(_text: &[u8]) -> anyhow::Result<Vec<u8>> {
+    bail!(ChunkError::CompressionUnavailable)
+}
+
+#[cfg(feature = "compression")]
+fn inflate_text(compressed: &[u8]) -> anyhow::Result<Vec<u8>> {
+    crate::zlib::decompress(compressed)
+}
+
+#[cfg(not(feature = "compression"))]
+fn inflate_text(_compressed: &[u8]) -> anyhow::Result<Vec<u8>> {
+    bail!(ChunkError::CompressionUnavailable)
+}
+
+fn latin1_bytes(s: &str) -> anyhow::Result<Vec<u8>> {
+    s.chars()
+        .map(|ch| u8::try_from(ch).map_err(|_| ChunkError::NonLatin1Text(ch).into()))
+        .collect()
+}
+
+fn latin1_string(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| *b as char).collect()
 }
 
+// The alternate form `{:#}` spreads the fields over several lines, with the type flags and a hex preview of the data
 impl Display for Chunk {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        write!(f, "Length: {}, Chunk type: {}, Data: {}, Crc: {}",
-               self.length, self.chunk_type, self.data_as_string().unwrap_or(Default::default()), self.crc())
+        if !f.alternate() {
+            return write!(f, "Length: {}, Chunk type: {}, Data: {}, Crc: {}",
+                          self.length, self.chunk_type, self.data_as_string().unwrap_or_default(), self.crc());
+        }
+        let chunk_type = &self.chunk_type;
+        let preview: Vec<String> = self.data.iter().take(16).map(|b| format!("{:02x}", b)).collect();
+        let ellipsis = if self.data.len() > 16 { " …" } else { "" };
+        let crc_status = if self.has_valid_crc() { "valid".to_string() } else { format!("expected {}", self.crc()) };
+        writeln!(f, "Chunk type: {}", chunk_type)?;
+        writeln!(f, "  Flags: {}, {}, {}",
+                 if chunk_type.is_critical() { "critical" } else { "ancillary" },
+                 if chunk_type.is_public() { "public" } else { "private" },
+                 if chunk_type.is_safe_to_copy() { "safe to copy" } else { "unsafe to copy" })?;
+        writeln!(f, "  Length: {}", self.length)?;
+        writeln!(f, "  Crc: {} ({})", self.stored_crc, crc_status)?;
+        write!(f, "  Data: {}{}", preview.join(" "), ellipsis)
     }
 }
 
@@ -88,6 +347,29 @@ impl TryFrom<&[u8]> for Chunk {
     type Error = anyhow::Error;
 
     fn try_from(value: &[u8]) -> anyhow::Result<Self> {
+        Chunk::parse(value, true)
+    }
+}
+
+impl Chunk {
+    /// Parses the chunk at the start of `value` and returns it with the number of bytes it spans, leaving the rest for the next chunk
+    pub fn try_from_prefix(value: &[u8]) -> anyhow::Result<(Chunk, usize)> {
+        Chunk::parse_prefix(value, true)
+    }
+
+    pub(crate) fn parse_prefix(value: &[u8], strict: bool) -> anyhow::Result<(Chunk, usize)> {
+        let length_bytes = value.get(0..4).ok_or(ChunkError::InvalidLength)?;
+        let length = u32::from_be_bytes([length_bytes[0], length_bytes[1], length_bytes[2], length_bytes[3]]);
+        let end = 12 + length as usize;
+        let Some(bytes) = value.get(..end) else {
+            // Too short for the declared length, parsing the whole input reports why or recovers a truncated chunk
+            return Chunk::parse(value, strict).map(|chunk| (chunk, value.len()));
+        };
+        Ok((Chunk::parse(bytes, strict)?, end))
+    }
+
+    // `strict` rejects CRC mismatches and truncated data instead of keeping them
+    fn parse(value: &[u8], strict: bool) -> anyhow::Result<Self> {
         let value = value.to_vec();
         // First 4 bytes specifying the data length
         let length_bytes = value.get(0..4).ok_or(ChunkError::InvalidLength)?;
@@ -103,34 +385,39 @@ impl TryFrom<&[u8]> for Chunk {
             bail!(ChunkError::LengthOverflow(length));
         }
 
-        // Data bytes
-        let data_bytes = if length > 0
-        { value.get(8..8 + length as usize).ok_or(ChunkError::MismatchDataLength)? } else { &[] };
-
-        // Crc bytes
-        let crc_bytes = value.get(8 + length as usize..).ok_or(ChunkError::InvalidCrcLength)?;
+        // Length, type, data and crc must cover the input exactly, so data can't spill into the crc
+        let expected_len = 8 + length as usize + 4;
+        if expected_len < value.len() || (strict && expected_len > value.len()) {
+            bail!(ChunkError::MismatchDataLength(expected_len, value.len()));
+        }
 
-        if crc_bytes.len() != 4 {
-            bail!(ChunkError::InvalidCrcLength);
+        let chunk_type_bytes = <[u8; 4]>::try_from(chunk_type_bytes).unwrap();
+        let chunk_type = ChunkType::try_from(chunk_type_bytes)
+            .ok()
+            .filter(|chunk_type| chunk_type.is_valid())
+            .ok_or(ChunkError::InvalidChunkType(chunk_type_bytes))?;
+
+        if expected_len > value.len() {
+            // The stored CRC never made it into the input, so the recovered data keeps a computed one
+            // and the bytes of a partial CRC are dropped rather than taken for data
+            let data_end = value.len().min(8 + length as usize);
+            let mut chunk = Chunk::new(chunk_type, value[8..data_end].to_vec());
+            chunk.truncated = true;
+            return Ok(chunk);
         }
 
+        let data_bytes = &value[8..8 + length as usize];
+        let crc_bytes = &value[8 + length as usize..];
 
         let crc_u32: Vec<u32> = crc_bytes.iter().map(|x| *x as u32).collect();
         let crc_num = (crc_u32[0] << 24) | (crc_u32[1] << 16) | (crc_u32[2] << 8) | crc_u32[3];
 
-
-        let chunk_type = ChunkType::try_from(
-            <[u8; 4]>::try_from(chunk_type_bytes).unwrap())?;
-
-        if !chunk_type.is_valid() {
-            bail!("Invalid chunk type {}", chunk_type);
-        }
-
-        let chunk = Chunk::new(chunk_type, data_bytes.to_vec());
+        let mut chunk = Chunk::new(chunk_type, data_bytes.to_vec());
         let chunk_crc = chunk.crc();
-        if chunk_crc != crc_num {
+        if strict && chunk_crc != crc_num {
             bail!(ChunkError::CrcMismatch(chunk_crc, crc_num));
         }
+        chunk.stored_crc = crc_num;
 
         Ok(chunk)
     }
@@ -175,6 +462,7 @@ mod tests {
     fn test_chunk_length() {
         let chunk = testing_chunk();
         assert_eq!(chunk.length(), 42);
+        assert_eq!(chunk.total_size(), chunk.as_bytes().len());
     }
 
     #[test]
@@ -191,12 +479,110 @@ mod tests {
         assert_eq!(chunk_string, expected_chunk_string);
     }
 
+    #[test]
+    fn test_chunk_alternate_display() {
+        let chunk = testing_chunk();
+        assert!(!format!("{}", chunk).contains('\n'));
+
+        let alternate = format!("{:#}", chunk);
+        assert_eq!(alternate, "Chunk type: RuSt\n  Flags: critical, private, safe to copy\n  Length: 42\n  \
+            Crc: 2882656334 (valid)\n  Data: 54 68 69 73 20 69 73 20 77 68 65 72 65 20 79 6f …");
+    }
+
+    #[test]
+    fn test_chunk_into_data() {
+        let chunk = testing_chunk();
+        let expected = chunk.data().to_vec();
+        assert_eq!(chunk.into_data(), expected);
+    }
+
+    #[test]
+    fn test_new_text() {
+        let chunk = Chunk::new_text("Author", "Geo").unwrap();
+        assert_eq!(&chunk.chunk_type().to_string(), "tEXt");
+        assert_eq!(chunk.data(), b"Author\0Geo");
+
+        let parsed = Chunk::try_from(chunk.as_bytes().as_ref()).unwrap();
+        assert_eq!(parsed.text_entry(), Some(("Author".to_string(), "Geo".to_string())));
+    }
+
+    #[test]
+    fn test_new_text_invalid_keyword() {
+        for keyword in ["", " Author", "Author ", "The  Author", "Auth\0or", "Autor\u{7f}", "Wörter☺"] {
+            assert!(Chunk::new_text(keyword, "text").is_err(), "{:?} should be rejected", keyword);
+        }
+        assert!(Chunk::new_text(&"k".repeat(79), "text").is_ok());
+        assert!(Chunk::new_text(&"k".repeat(80), "text").is_err());
+    }
+
+    #[test]
+    #[cfg(feature = "compression")]
+    fn test_new_ztxt() {
+        let text = "This is where your secret message will be! ".repeat(10);
+        let chunk = Chunk::new_ztxt("Comment", &text).unwrap();
+        assert_eq!(&chunk.chunk_type().to_string(), "zTXt");
+        assert!(chunk.data().starts_with(b"Comment\0\0"));
+        assert!((chunk.length() as usize) < text.len());
+
+        let parsed = Chunk::try_from(chunk.as_bytes().as_ref()).unwrap();
+        assert_eq!(parsed.ztxt_entry().unwrap(), Some(("Comment".to_string(), text)));
+        assert_eq!(parsed.text_entry(), None);
+    }
+
+    fn international_text(compressed: bool) -> InternationalText {
+        InternationalText {
+            keyword: "Title".to_string(),
+            language: "de-CH".to_string(),
+            translated_keyword: "Titel".to_string(),
+            text: "Grüezi mitenand ☺ ".repeat(8),
+            compressed,
+        }
+    }
+
+    #[test]
+    fn test_new_itxt() {
+        let entry = international_text(false);
+        let chunk = Chunk::new_itxt(&entry).unwrap();
+        assert!(chunk.data().starts_with(b"Title\0\0\0de-CH\0Titel\0Gr\xc3\xbcezi"));
+
+        let parsed = Chunk::try_from(chunk.as_bytes().as_ref()).unwrap();
+        assert_eq!(parsed.itxt_entry().unwrap(), Some(entry));
+        assert_eq!(parsed.text_entry(), None);
+
+        assert!(Chunk::new_itxt(&InternationalText { language: "en GB".to_string(), ..international_text(false) }).is_err());
+        let empty = Chunk::new(ChunkType::from_str("iTXt").unwrap(), b"Title\0\0\0".to_vec());
+        assert_eq!(empty.itxt_entry().unwrap(), None);
+    }
+
+    #[test]
+    #[cfg(feature = "compression")]
+    fn test_new_itxt_compressed() {
+        let entry = international_text(true);
+        let chunk = Chunk::new_itxt(&entry).unwrap();
+        assert!(chunk.data().starts_with(b"Title\0\x01\0de-CH\0Titel\0"));
+        assert!((chunk.length() as usize) < entry.text.len());
+        assert_eq!(chunk.itxt_entry().unwrap(), Some(entry));
+    }
+
     #[test]
     fn test_chunk_crc() {
         let chunk = testing_chunk();
         assert_eq!(chunk.crc(), 2882656334);
     }
 
+    #[test]
+    fn test_compute_crc() {
+        let chunk_type = ChunkType::from_str("RuSt").unwrap();
+        let crc = Chunk::compute_crc(&chunk_type, b"This is where your secret message will be!");
+        assert_eq!(crc, 2882656334);
+    }
+
+    #[test]
+    fn test_chunk_bytes_iter() {
+        let chunk = testing_chunk();
+        assert_eq!(chunk.bytes_iter().collect::<Vec<_>>(), chunk.as_bytes());
+    }
+
     #[test]
     fn test_valid_chunk_from_bytes() {
         let data_length: u32 = 42;
@@ -245,6 +631,102 @@ mod tests {
         assert!(chunk.is_err());
     }
 
+    #[test]
+    fn test_chunk_with_trailing_bytes() {
+        let mut bytes = testing_chunk().as_bytes();
+        bytes.extend_from_slice(&[1, 2]);
+
+        let err = Chunk::try_from(bytes.as_ref()).err().unwrap();
+        let err = err.downcast_ref::<ChunkError>().unwrap();
+        assert!(matches!(err, ChunkError::MismatchDataLength(54, 56)));
+        assert_eq!(err.to_string(), "Chunk with the specified length must be 54 bytes in total, got 56");
+
+        let short = &testing_chunk().as_bytes()[..50];
+        assert!(matches!(
+            Chunk::try_from(short).err().unwrap().downcast_ref::<ChunkError>(),
+            Some(ChunkError::MismatchDataLength(54, 50))
+        ));
+    }
+
+    #[test]
+    fn test_lenient_recovers_truncated_chunk() {
+        let short = &testing_chunk().as_bytes()[..30];
+        assert!(Chunk::try_from(short).is_err());
+
+        let chunk = Chunk::from_bytes_lenient(short).unwrap();
+        assert!(chunk.is_truncated());
+        assert_eq!(chunk.chunk_type().to_string(), "RuSt");
+        assert_eq!(chunk.data(), b"This is where your sec");
+        assert!(!testing_chunk().is_truncated());
+    }
+
+    #[test]
+    fn test_lenient_drops_a_partial_crc() {
+        let original = testing_chunk();
+        let bytes = original.as_bytes();
+        let chunk = Chunk::from_bytes_lenient(&bytes[..bytes.len() - 2]).unwrap();
+        assert!(chunk.is_truncated());
+        assert_eq!(chunk.data(), original.data());
+        assert_eq!(chunk.crc(), original.crc());
+    }
+
+    #[test]
+    fn test_try_from_prefix() {
+        let first = testing_chunk();
+        let second = Chunk::new(ChunkType::from_str("ruSt").unwrap(), b"second".to_vec());
+        let mut bytes = first.as_bytes();
+        bytes.extend(second.as_bytes());
+
+        let (chunk, consumed) = Chunk::try_from_prefix(&bytes).unwrap();
+        assert_eq!(chunk, first);
+        assert_eq!(consumed, first.total_size());
+        let (chunk, rest) = Chunk::try_from_prefix(&bytes[consumed..]).unwrap();
+        assert_eq!(chunk, second);
+        assert_eq!(consumed + rest, bytes.len());
+
+        assert!(matches!(
+            Chunk::try_from_prefix(&bytes[..20]).err().unwrap().downcast_ref::<ChunkError>(),
+            Some(ChunkError::MismatchDataLength(54, 20))
+        ));
+    }
+
+    #[test]
+    fn test_invalid_chunk_type_renders_raw_bytes() {
+        let chunk_data: Vec<u8> = [0, 0, 0, 0, 32, 117, 0xFF, 116, 0, 0, 0, 0].to_vec();
+        let err = Chunk::try_from(chunk_data.as_ref()).err().unwrap();
+        assert_eq!(err.to_string(), "Invalid chunk type \\x20u\\xfft");
+
+        let chunk_data: Vec<u8> = [0, 0, 0, 0, 82, 117, 115, 116, 0, 0, 0, 0].to_vec();
+        let err = Chunk::try_from(chunk_data.as_ref()).err().unwrap();
+        assert_eq!(err.to_string(), "Invalid chunk type Rust");
+    }
+
+    #[test]
+    fn test_lenient_chunk_keeps_bad_crc() {
+        let mut chunk_bytes = testing_chunk().as_bytes();
+        let last = chunk_bytes.len() - 1;
+        chunk_bytes[last] ^= 1;
+
+        assert!(Chunk::try_from(chunk_bytes.as_ref()).is_err());
+        let chunk = Chunk::from_bytes_lenient(chunk_bytes.as_ref()).unwrap();
+        assert!(!chunk.has_valid_crc());
+        assert_eq!(chunk.stored_crc(), 2882656334 ^ 1);
+        assert_eq!(chunk.crc(), 2882656334);
+        assert_eq!(chunk.as_bytes(), chunk_bytes);
+    }
+
+    #[test]
+    fn test_recompute_crc() {
+        let mut chunk_bytes = testing_chunk().as_bytes();
+        let last = chunk_bytes.len() - 1;
+        chunk_bytes[last] ^= 1;
+
+        let mut chunk = Chunk::from_bytes_lenient(chunk_bytes.as_ref()).unwrap();
+        chunk.recompute_crc();
+        assert!(chunk.has_valid_crc());
+        assert_eq!(chunk.as_bytes(), testing_chunk().as_bytes());
+    }
+
     #[test]
     pub fn test_chunk_trait_impls() {
         let data_length: u32 = 42;
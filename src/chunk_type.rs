@@ -13,7 +13,43 @@ pub enum ChunkTypeError {
     InvalidChunkByte(u8),
 }
 
-#[derive(PartialEq, Debug, Eq, Clone)]
+impl ChunkTypeError {
+    /// Stable, machine-readable name of the error variant
+    pub fn kind(&self) -> &'static str {
+        match self {
+            ChunkTypeError::WrongStringByteLength(_) => "WrongStringByteLength",
+            ChunkTypeError::InvalidChunkByte(_) => "InvalidChunkByte",
+        }
+    }
+}
+
+// Chunk types defined by the PNG specification and its registered extensions
+const STANDARD_TYPES: [(&[u8; 4], &str); 21] = [
+    (b"IHDR", "image header"),
+    (b"PLTE", "palette"),
+    (b"IDAT", "image data"),
+    (b"IEND", "image trailer"),
+    (b"tRNS", "transparency"),
+    (b"cHRM", "primary chromaticities and white point"),
+    (b"gAMA", "image gamma"),
+    (b"iCCP", "embedded ICC profile"),
+    (b"sBIT", "significant bits"),
+    (b"sRGB", "standard RGB color space"),
+    (b"cICP", "coding-independent code points"),
+    (b"tEXt", "Latin-1 text"),
+    (b"zTXt", "compressed Latin-1 text"),
+    (b"iTXt", "international UTF-8 text"),
+    (b"bKGD", "background color"),
+    (b"hIST", "palette histogram"),
+    (b"pHYs", "physical pixel dimensions"),
+    (b"sPLT", "suggested palette"),
+    (b"eXIf", "Exif metadata"),
+    (b"tIME", "last modification time"),
+    (b"acTL", "animation control"),
+];
+
+// Ordering follows the type bytes, the big-endian `num` compares the same way
+#[derive(PartialEq, Debug, Eq, Clone, PartialOrd, Ord)]
 pub struct ChunkType {
     num: u32,
 }
@@ -33,28 +69,44 @@ impl ChunkType {
             .unwrap()
     }
 
-    fn is_critical(&self) -> bool {
+    pub fn is_critical(&self) -> bool {
         self.nth_byte(0).unwrap() & (1 << 5) == 0
     }
 
-    fn is_public(&self) -> bool {
+    pub fn is_public(&self) -> bool {
         self.nth_byte(1).unwrap() & (1 << 5) == 0
     }
 
-    fn is_reserved_bit_valid(&self) -> bool {
+    pub fn is_reserved_bit_valid(&self) -> bool {
         self.nth_byte(2).unwrap() & (1 << 5) == 0
     }
 
-    fn is_safe_to_copy(&self) -> bool {
+    pub fn is_safe_to_copy(&self) -> bool {
         self.nth_byte(3).unwrap() & (1 << 5) != 0
     }
 
+    /// Short description of a standard chunk type, `None` for custom and private types
+    pub fn description(&self) -> Option<&'static str> {
+        STANDARD_TYPES.iter().find(|(name, _)| *self == **name).map(|(_, description)| *description)
+    }
+
+    /// Deterministic private, ancillary, safe-to-copy type for `n`, written in base 26 over the letters,
+    /// distinct for every `n` below 26^4 and wrapping around after that
+    #[cfg(feature = "counter-types")]
+    pub fn from_counter(n: u32) -> ChunkType {
+        let letter = |place: u32| n / 26u32.pow(place) % 26;
+        let num = [(b'a', 3), (b'a', 2), (b'A', 1), (b'a', 0)]
+            .iter()
+            .fold(0u32, |num, (base, place)| num << 8 | (*base as u32 + letter(*place)));
+        ChunkType { num }
+    }
+
     pub(crate) fn is_valid(&self) -> bool {
         self.is_reserved_bit_valid()
     }
 
     fn nth_byte(&self, idx: usize) -> anyhow::Result<u8> {
-        if idx > 4 {
+        if idx > 3 {
             bail!("Missing attribute: {}", 1);
         }
         Ok((self.num >> (24 - idx * 8)) as u8)
@@ -84,6 +136,12 @@ impl TryFrom<[u8; 4]> for ChunkType {
     }
 }
 
+impl PartialEq<[u8; 4]> for ChunkType {
+    fn eq(&self, other: &[u8; 4]) -> bool {
+        self.bytes() == *other
+    }
+}
+
 impl FromStr for ChunkType {
     type Err = anyhow::Error;
 
@@ -118,6 +176,21 @@ mod tests {
         assert_eq!(expected, actual);
     }
 
+    #[test]
+    pub fn test_chunk_type_eq_bytes() {
+        let chunk_type = ChunkType::from_str("IHDR").unwrap();
+        assert!(chunk_type == *b"IHDR");
+        assert!(chunk_type != *b"IEND");
+    }
+
+    #[test]
+    pub fn test_chunk_type_ord() {
+        let mut types: Vec<ChunkType> = ["ruSt", "IHDR", "IEND", "RuSt"].iter().map(|t| ChunkType::from_str(t).unwrap()).collect();
+        types.sort();
+        let types: Vec<String> = types.iter().map(ChunkType::to_string).collect();
+        assert_eq!(types, ["IEND", "IHDR", "RuSt", "ruSt"]);
+    }
+
     #[test]
     pub fn test_chunk_type_is_critical() {
         let chunk = ChunkType::from_str("RuSt").unwrap();
@@ -181,6 +254,32 @@ mod tests {
         assert!(chunk.is_err());
     }
 
+    #[test]
+    pub fn test_chunk_type_description() {
+        assert_eq!(ChunkType::from_str("IHDR").unwrap().description(), Some("image header"));
+        assert_eq!(ChunkType::from_str("IDAT").unwrap().description(), Some("image data"));
+        assert_eq!(ChunkType::from_str("ruSt").unwrap().description(), None);
+    }
+
+    #[test]
+    #[cfg(feature = "counter-types")]
+    pub fn test_chunk_type_from_counter() {
+        let (first, second) = (ChunkType::from_counter(0), ChunkType::from_counter(1));
+        assert_ne!(first, second);
+        assert_eq!(first.to_string(), "aaAa");
+        assert_eq!(ChunkType::from_counter(27).to_string(), "aaBb");
+        for chunk_type in [first, second, ChunkType::from_counter(u32::MAX)] {
+            assert!(chunk_type.is_valid());
+            assert!(!chunk_type.is_critical() && !chunk_type.is_public() && chunk_type.is_safe_to_copy());
+        }
+    }
+
+    #[test]
+    pub fn test_nth_byte_out_of_range() {
+        let chunk = ChunkType::from_str("RuSt").unwrap();
+        assert!(chunk.nth_byte(4).is_err());
+    }
+
     #[test]
     pub fn test_chunk_type_string() {
         let chunk = ChunkType::from_str("RuSt").unwrap();
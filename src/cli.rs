@@ -1,5 +1,6 @@
-use clap::{Parser, Subcommand};
-use crate::chunk_type::ChunkType;
+use clap::{Parser, Subcommand, ValueEnum};
+use pngme::chunk_type::ChunkType;
+use pngme::png::Png;
 
 
 #[derive(Parser)]
@@ -10,6 +11,82 @@ use crate::chunk_type::ChunkType;
 pub struct Cli {
     #[command(subcommand)]
     pub command: Commands,
+
+    /// How errors are reported on stderr
+    #[arg(long, global = true, value_enum, default_value_t = ErrorFormat::Text)]
+    pub error_format: ErrorFormat,
+
+    /// Keep the CRCs stored in the input file instead of rejecting or recomputing mismatching ones, other damage is still an error
+    #[arg(long, global = true)]
+    pub no_crc_recompute: bool,
+
+    /// Read the input as a bare chunk stream without the 8 byte PNG signature
+    #[arg(long, global = true)]
+    pub no_signature: bool,
+
+    /// Encoding of input files, `auto` detects raw PNG, hex and base64 by their leading bytes
+    #[arg(long, global = true, value_enum, default_value_t = InputFormat::Auto)]
+    pub input_format: InputFormat,
+
+    /// Give rewritten files the modification time the input file had
+    #[arg(long, global = true)]
+    pub preserve_mtime: bool,
+
+    /// Keep the previous content of an overwritten file as `<file>.bak`
+    #[arg(long, global = true)]
+    pub backup: bool,
+}
+
+#[derive(ValueEnum, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ErrorFormat {
+    Text,
+    Json,
+}
+
+#[derive(ValueEnum, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum InputFormat {
+    Auto,
+    Png,
+    Base64,
+    Hex,
+}
+
+#[derive(ValueEnum, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum SortOrder {
+    Index,
+    Type,
+    Size,
+}
+
+#[derive(ValueEnum, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum PrintFormat {
+    Text,
+    CArray,
+}
+
+#[derive(ValueEnum, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ChecksumAlgorithm {
+    Crc32,
+    #[cfg(feature = "sha256")]
+    Sha256,
+    #[cfg(feature = "blake3")]
+    Blake3,
+}
+
+#[derive(ValueEnum, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum EmbedMode {
+    /// In an ancillary chunk of its own
+    Chunk,
+    /// In the least significant bits of the image samples, surviving tools that strip chunks
+    #[cfg(feature = "compression")]
+    Lsb,
+}
+
+#[derive(ValueEnum, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum PayloadCompression {
+    None,
+    #[cfg(feature = "compression")]
+    Deflate,
 }
 
 #[derive(Subcommand)]
@@ -19,22 +96,149 @@ pub enum Commands {
         #[arg(short, long)]
         file_path: String,
 
-        #[arg(short, long)]
-        chunk_type: ChunkType,
+        #[arg(short, long, required_unless_present_any = ["text_keyword", "after_iend", "chunk_type_file", "mode", "text"], conflicts_with_all = ["text_keyword", "after_iend", "chunk_type_file"])]
+        chunk_type: Option<ChunkType>,
+
+        /// Where the message is stored, the default is a chunk. `chunk` needs a chunk type, `lsb` takes none
+        #[arg(long, conflicts_with_all = ["text_keyword", "after_iend"])]
+        mode: Option<EmbedMode>,
+
+        /// Read the chunk type from the first 4 bytes of this file
+        #[arg(long, conflicts_with_all = ["text_keyword", "after_iend"])]
+        chunk_type_file: Option<String>,
+
+        #[arg(short, long, required_unless_present = "text")]
+        message: Option<String>,
+
+        /// Store a standard tEXt chunk given as KEY=VALUE, readable by image viewers and exiftool
+        #[arg(long, value_name = "KEY=VALUE", value_parser = parse_text_entry, conflicts_with_all = ["message", "text_keyword", "after_iend", "chunk_type", "chunk_type_file", "mode", "hmac_key"])]
+        text: Option<(String, String)>,
+
+        /// Compress the message before embedding it, `decode` detects and undoes this on its own.
+        /// Text stored under a keyword is written as a standard zTXt chunk instead.
+        /// zstd is not offered, pngme only bundles a deflate codec
+        #[arg(long, conflicts_with = "after_iend")]
+        compress: Option<PayloadCompression>,
+
+        /// Store an HMAC-SHA256 tag of the message under this key, checked by `decode --verify-key`
+        #[arg(long, conflicts_with_all = ["text_keyword", "after_iend"])]
+        hmac_key: Option<String>,
+
+        /// Store the message in a standard tEXt chunk under this keyword
+        #[arg(short, long, conflicts_with = "after_iend")]
+        text_keyword: Option<String>,
+
+        /// Store the text in a UTF-8 iTXt chunk instead of a Latin-1 tEXt or zTXt chunk
+        #[arg(long, conflicts_with_all = ["after_iend", "chunk_type", "chunk_type_file", "mode", "hmac_key"])]
+        itxt: bool,
+
+        /// Language tag of the iTXt text, such as `en-GB`
+        #[arg(long, requires = "itxt")]
+        language: Option<String>,
+
+        /// The keyword translated into the language of the iTXt text
+        #[arg(long, requires = "itxt")]
+        translated_keyword: Option<String>,
+
+        /// Append the message after the IEND chunk instead of in a chunk, replacing one stored there before
+        #[arg(long)]
+        after_iend: bool,
 
         #[arg(short, long)]
-        message: String,
+        output_file: Option<String>,
 
+        /// Overwrite the input file without asking for confirmation
         #[arg(short, long)]
-        output_file: Option<String>
+        yes: bool,
+
+        /// Allow encoding into a critical chunk type, which can corrupt the image
+        #[arg(long)]
+        allow_critical: bool,
+
+        /// Print the chunks that would change instead of writing the file
+        #[arg(long)]
+        dry_run: bool,
+
+        /// Encode the message N times and report the resulting size, for stress testing
+        #[arg(long, hide = true, value_parser = clap::value_parser!(u32).range(1..))]
+        repeat: Option<u32>,
     },
     /// Decodes a message from a PNG file
     Decode {
         #[arg(short, long)]
         file_path: String,
 
+        #[arg(short, long, required_unless_present_any = ["text_keyword", "after_iend", "chunk_type_file", "mode", "list_text"], conflicts_with_all = ["text_keyword", "after_iend", "chunk_type_file"])]
+        chunk_type: Option<ChunkType>,
+
+        /// Where the message is stored, the default is a chunk. `chunk` needs a chunk type, `lsb` takes none
+        #[arg(long, conflicts_with_all = ["text_keyword", "after_iend"])]
+        mode: Option<EmbedMode>,
+
+        /// Read the chunk type from the first 4 bytes of this file
+        #[arg(long, conflicts_with_all = ["text_keyword", "after_iend"])]
+        chunk_type_file: Option<String>,
+
+        /// Read the message from the tEXt, zTXt or iTXt chunk with this keyword
+        #[arg(short, long, conflicts_with = "after_iend")]
+        text_keyword: Option<String>,
+
+        /// Read the message `encode --after-iend` stored after the IEND chunk
+        #[arg(long)]
+        after_iend: bool,
+
+        /// Print the keyword and text of every tEXt, zTXt and iTXt chunk instead of one message
+        #[arg(long, conflicts_with_all = ["chunk_type", "chunk_type_file", "text_keyword", "after_iend", "mode"])]
+        list_text: bool,
+
+        /// Decode the N-th chunk of the type instead of the first, counting from 0
+        #[arg(short, long, default_value_t = 0, conflicts_with_all = ["text_keyword", "after_iend"])]
+        index: usize,
+
+        /// Comma-separated chunk types that may be decoded, any other type is refused
+        #[arg(long, value_delimiter = ',')]
+        allow_types: Option<Vec<ChunkType>>,
+
+        /// Show invalid UTF-8 as replacement characters instead of failing
+        #[arg(long)]
+        lossy: bool,
+
+        /// Fail unless the message carries an HMAC-SHA256 tag matching this key
+        #[arg(long, conflicts_with_all = ["text_keyword", "after_iend"])]
+        verify_key: Option<String>,
+    },
+    /// Embeds the bytes of any file into chunks of a PNG file
+    EncodeFile {
+        #[arg(short, long)]
+        file_path: String,
+
+        #[arg(short, long)]
+        payload_file: String,
+
         #[arg(short, long)]
         chunk_type: ChunkType,
+
+        #[arg(short, long)]
+        output_file: Option<String>,
+
+        /// Largest number of payload bytes stored per chunk, bigger files are split across numbered chunks
+        #[arg(long, default_value_t = Png::SPLIT_PIECE_SIZE)]
+        piece_size: usize,
+
+        /// Allow encoding into a critical chunk type, which can corrupt the image
+        #[arg(long)]
+        allow_critical: bool,
+    },
+    /// Writes the bytes embedded with `encode-file` back to a file
+    DecodeFile {
+        #[arg(short, long)]
+        file_path: String,
+
+        #[arg(short, long)]
+        chunk_type: ChunkType,
+
+        #[arg(short, long)]
+        output_file: String,
     },
     /// Removes a message from a PNG file
     Remove {
@@ -43,10 +247,322 @@ pub enum Commands {
 
         #[arg(short, long)]
         chunk_type: ChunkType,
+
+        /// Print the chunks that would change instead of writing the file
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Succeeds only if the message decoded from a chunk equals the expected value
+    Expect {
+        #[arg(short, long)]
+        file_path: String,
+
+        #[arg(short, long)]
+        chunk_type: ChunkType,
+
+        #[arg(short, long)]
+        value: String,
+    },
+    /// Lists the chunks added, removed and modified going from one PNG file to another
+    Diff {
+        #[arg(short, long)]
+        file_path: String,
+
+        /// The changed file compared against `--file-path`
+        #[arg(long)]
+        other: String,
     },
     /// Prints a list of PNG chunks that can be searched for messages
     Print {
         #[arg(short, long)]
         file_path: String,
+
+        /// Only print chunks whose type starts with this prefix (case-sensitive)
+        #[arg(short, long)]
+        type_prefix: Option<String>,
+
+        /// Only print the first N chunks
+        #[arg(short, long)]
+        limit: Option<usize>,
+
+        /// Order of the printed chunks, the file itself is not changed
+        #[arg(short, long, value_enum, default_value_t = SortOrder::Index)]
+        sort: SortOrder,
+
+        /// Reverse the printed order, e.g. largest first with `--sort size`
+        #[arg(short, long)]
+        reverse: bool,
+
+        /// `c-array` renders each chunk's data as a C byte array named after its type and position
+        #[arg(long, value_enum, default_value_t = PrintFormat::Text)]
+        format: PrintFormat,
+
+        /// Describe each standard chunk type, e.g. "IHDR: image header"
+        #[arg(short, long)]
+        annotate: bool,
+    },
+    /// Writes the data of ancillary chunks to separate files in a directory
+    Extract {
+        #[arg(short, long)]
+        file_path: String,
+
+        #[arg(short, long)]
+        output_dir: String,
+
+        /// Only extract chunks of this type instead of every ancillary chunk
+        #[arg(short, long)]
+        chunk_type: Option<ChunkType>,
+    },
+    /// Succeeds only if the file starts with the PNG signature, reading nothing past it
+    IsPng {
+        #[arg(short, long)]
+        file_path: String,
     },
-}
\ No newline at end of file
+    /// Prints a hex digest of the whole file
+    Checksum {
+        #[arg(short, long)]
+        file_path: String,
+
+        #[arg(short, long, value_enum, default_value_t = ChecksumAlgorithm::Crc32)]
+        algorithm: ChecksumAlgorithm,
+    },
+    /// Checks PNG files for structural and CRC problems
+    Verify {
+        /// One or more files, each is checked even if an earlier one fails unless `--fail-fast` is set
+        #[arg(short, long, num_args = 1.., required = true)]
+        file_path: Vec<String>,
+
+        /// Stop at the first file with problems instead of checking the rest
+        #[arg(long)]
+        fail_fast: bool,
+    },
+    /// Checks a PNG file against the spec, listing each violation with its byte offset and severity
+    Validate {
+        #[arg(short, long)]
+        file_path: String,
+    },
+    /// Prints a one-screen summary of the signature, structure and CRCs of a PNG file
+    Check {
+        #[arg(short, long)]
+        file_path: String,
+    },
+    /// Reports how many bytes each embedding mode can store in a PNG file
+    Capacity {
+        #[arg(short, long)]
+        file_path: String,
+    },
+    /// Lists non-standard chunks, high-entropy or oversized ancillary chunks and data after IEND that may hide content
+    Scan {
+        #[arg(short, long)]
+        file_path: String,
+    },
+    /// Lists the casing flags of every chunk and marks unusual combinations
+    AuditFlags {
+        #[arg(short, long)]
+        file_path: String,
+    },
+    /// Lists the chunks a viewer or editor following the given policy would drop, without changing the file
+    WhatIfStrip {
+        #[arg(short, long)]
+        file_path: String,
+
+        /// Drop ancillary chunks of unknown type that are not safe to copy
+        #[arg(long, required_unless_present = "drop_all_ancillary")]
+        drop_unsafe_unknown: bool,
+
+        /// Drop every ancillary chunk
+        #[arg(long)]
+        drop_all_ancillary: bool,
+    },
+    /// Removes the ancillary chunks from a PNG file, except for the kept types
+    Strip {
+        #[arg(short, long)]
+        file_path: String,
+
+        /// Comma-separated ancillary chunk types to keep
+        #[arg(short, long, value_delimiter = ',')]
+        keep: Vec<ChunkType>,
+
+        #[arg(short, long)]
+        output_file: Option<String>,
+    },
+    /// Removes the tEXt, zTXt and iTXt chunks from a PNG file, keeping every other chunk
+    StripText {
+        #[arg(short, long)]
+        file_path: String,
+
+        #[arg(short, long)]
+        output_file: Option<String>,
+    },
+    /// Rewrites a PNG file with its chunks in canonical order and every CRC recomputed
+    Normalize {
+        #[arg(short, long)]
+        file_path: String,
+
+        #[arg(short, long)]
+        output_file: Option<String>,
+    },
+    /// Minifies a PNG file for the web, keeping only critical chunks with the image data in one IDAT chunk
+    Sanitize {
+        #[arg(short, long)]
+        file_path: String,
+
+        #[arg(short, long)]
+        output_file: Option<String>,
+
+        /// Keep any bytes stored after the IEND chunk
+        #[arg(long)]
+        keep_trailing: bool,
+    },
+    /// Stores a JSON object as metadata in a PNG file
+    SetMeta {
+        #[arg(short, long)]
+        file_path: String,
+
+        #[arg(short, long)]
+        json: String,
+
+        #[arg(short, long)]
+        output_file: Option<String>,
+
+        /// Print the chunks that would change instead of writing the file
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Prints the JSON metadata stored in a PNG file
+    GetMeta {
+        #[arg(short, long)]
+        file_path: String,
+    },
+    /// Copies the first chunk of a type from one PNG file into another under a new type
+    Copy {
+        #[arg(long)]
+        source: String,
+
+        #[arg(long)]
+        source_type: ChunkType,
+
+        #[arg(long)]
+        dest: String,
+
+        #[arg(long)]
+        dest_type: ChunkType,
+
+        #[arg(short, long)]
+        output_file: Option<String>,
+    },
+    /// Compresses a file and hides it across chunks of one type in a PNG file
+    #[cfg(feature = "compression")]
+    Hide {
+        #[arg(long)]
+        cover_png: String,
+
+        #[arg(short, long)]
+        payload_file: String,
+
+        #[arg(short, long)]
+        chunk_type: ChunkType,
+
+        #[arg(short, long)]
+        output_file: Option<String>,
+
+        /// Store the CRC-32 of the whole file so `reveal` refuses a payload with a dropped or reordered piece
+        #[arg(long)]
+        integrity: bool,
+    },
+    /// Reconstructs a file hidden with `hide`
+    #[cfg(feature = "compression")]
+    Reveal {
+        #[arg(short, long)]
+        file_path: String,
+
+        #[arg(short, long)]
+        chunk_type: ChunkType,
+
+        #[arg(short, long)]
+        output_file: String,
+    },
+    /// Measures parse, append and serialize throughput on a PNG file
+    #[command(hide = true)]
+    Bench {
+        #[arg(short, long)]
+        file_path: String,
+
+        #[arg(short, long, default_value_t = 100)]
+        iterations: usize,
+    },
+    /// Wraps the bytes of a file into a single serialized chunk, not a full PNG
+    MakeChunk {
+        #[arg(short, long)]
+        chunk_type: ChunkType,
+
+        #[arg(short, long)]
+        input_file: String,
+
+        #[arg(short, long)]
+        output_file: String,
+    },
+}
+
+impl Commands {
+    /// The file errors are reported against unless they carry their own, `None` for batches of files
+    pub fn file_path(&self) -> Option<&str> {
+        let file_path = match self {
+            Commands::Encode { file_path, .. }
+            | Commands::Decode { file_path, .. }
+            | Commands::EncodeFile { file_path, .. }
+            | Commands::DecodeFile { file_path, .. }
+            | Commands::Remove { file_path, .. }
+            | Commands::Expect { file_path, .. }
+            | Commands::Diff { file_path, .. }
+            | Commands::Print { file_path, .. }
+            | Commands::Extract { file_path, .. }
+            | Commands::IsPng { file_path }
+            | Commands::Checksum { file_path, .. }
+            | Commands::Validate { file_path }
+            | Commands::Check { file_path }
+            | Commands::Capacity { file_path }
+            | Commands::Scan { file_path }
+            | Commands::AuditFlags { file_path }
+            | Commands::WhatIfStrip { file_path, .. }
+            | Commands::Strip { file_path, .. }
+            | Commands::StripText { file_path, .. }
+            | Commands::Normalize { file_path, .. }
+            | Commands::Sanitize { file_path, .. }
+            | Commands::SetMeta { file_path, .. }
+            | Commands::GetMeta { file_path }
+            | Commands::Bench { file_path, .. } => file_path,
+            // Each failing file of the batch is reported with its own path
+            Commands::Verify { .. } => return None,
+            Commands::MakeChunk { input_file, .. } => input_file,
+            Commands::Copy { source, .. } => source,
+            #[cfg(feature = "compression")]
+            Commands::Hide { cover_png, .. } => cover_png,
+            #[cfg(feature = "compression")]
+            Commands::Reveal { file_path, .. } => file_path,
+        };
+        Some(file_path)
+    }
+}
+
+// Splits a KEY=VALUE argument at the first `=`, so the text itself may contain more
+fn parse_text_entry(value: &str) -> Result<(String, String), String> {
+    let (keyword, text) = value.split_once('=').ok_or_else(|| format!("expected KEY=VALUE, got: {}", value))?;
+    if keyword.is_empty() {
+        return Err("the keyword before `=` must not be empty".to_string());
+    }
+    Ok((keyword.to_string(), text.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_text_entry() {
+        assert_eq!(parse_text_entry("Comment=a=b").unwrap(), ("Comment".to_string(), "a=b".to_string()));
+        assert_eq!(parse_text_entry("Title=").unwrap(), ("Title".to_string(), String::new()));
+        assert!(parse_text_entry("no separator").is_err());
+        assert!(parse_text_entry("=text").is_err());
+    }
+}
@@ -0,0 +1,45 @@
+use anyhow::bail;
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum HexError {
+    #[error("Invalid hex digit '{0}' at position {1}")]
+    InvalidDigit(char, usize),
+    #[error("Hex input must have an even number of digits, got {0}")]
+    OddLength(usize),
+}
+
+/// Lowercase hex digits, two per byte
+pub fn encode(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Decodes hex digits of either case, ASCII whitespace between digits is ignored
+pub fn decode(s: &str) -> anyhow::Result<Vec<u8>> {
+    let digits = s.char_indices()
+        .filter(|(_, ch)| !ch.is_ascii_whitespace())
+        .map(|(pos, ch)| ch.to_digit(16).map(|d| d as u8).ok_or(HexError::InvalidDigit(ch, pos)))
+        .collect::<Result<Vec<u8>, HexError>>()?;
+    if digits.len() % 2 != 0 {
+        bail!(HexError::OddLength(digits.len()));
+    }
+    Ok(digits.chunks(2).map(|pair| pair[0] << 4 | pair[1]).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let bytes = [0x89, 0x50, 0x4E, 0x47, 0x00, 0xFF];
+        assert_eq!(encode(&bytes), "89504e4700ff");
+        assert_eq!(decode("89504E47 00ff\n").unwrap(), bytes);
+    }
+
+    #[test]
+    fn test_decode_invalid() {
+        assert!(decode("abc").is_err());
+        assert!(decode("zz").is_err());
+    }
+}
@@ -0,0 +1,168 @@
+use std::convert::TryFrom;
+
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum IhdrError {
+    #[error("IHDR chunk must be 13 bytes long, got {0}")]
+    InvalidLength(usize),
+    #[error("Image width and height must be between 1 and 2^31 - 1, got {0}x{1}")]
+    InvalidDimensions(u32, u32),
+    #[error("Unknown color type {0}")]
+    InvalidColorType(u8),
+    #[error("Bit depth {0} is not allowed for color type {1}")]
+    InvalidBitDepth(u8, u8),
+    #[error("Unknown compression method {0}")]
+    InvalidCompressionMethod(u8),
+    #[error("Unknown filter method {0}")]
+    InvalidFilterMethod(u8),
+    #[error("Unknown interlace method {0}")]
+    InvalidInterlaceMethod(u8),
+}
+
+impl IhdrError {
+    /// Stable, machine-readable name of the error variant
+    pub fn kind(&self) -> &'static str {
+        match self {
+            IhdrError::InvalidLength(_) => "InvalidIhdrLength",
+            IhdrError::InvalidDimensions(..) => "InvalidDimensions",
+            IhdrError::InvalidColorType(_) => "InvalidColorType",
+            IhdrError::InvalidBitDepth(..) => "InvalidBitDepth",
+            IhdrError::InvalidCompressionMethod(_) => "InvalidCompressionMethod",
+            IhdrError::InvalidFilterMethod(_) => "InvalidFilterMethod",
+            IhdrError::InvalidInterlaceMethod(_) => "InvalidInterlaceMethod",
+        }
+    }
+}
+
+/// The image header, parsed from the data of the IHDR chunk and checked against the combinations the spec allows
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Ihdr {
+    pub width: u32,
+    pub height: u32,
+    pub bit_depth: u8,
+    /// 0 grayscale, 2 truecolor, 3 indexed, 4 grayscale with alpha, 6 truecolor with alpha
+    pub color_type: u8,
+    pub compression_method: u8,
+    pub filter_method: u8,
+    /// 0 for none, 1 for Adam7
+    pub interlace_method: u8,
+}
+
+impl Ihdr {
+    /// Samples per pixel, a palette index counts as one
+    pub fn channels(&self) -> usize {
+        match self.color_type {
+            2 => 3,
+            4 => 2,
+            6 => 4,
+            _ => 1,
+        }
+    }
+
+    pub fn bits_per_pixel(&self) -> usize {
+        self.channels() * self.bit_depth as usize
+    }
+
+    pub fn is_interlaced(&self) -> bool {
+        self.interlace_method == 1
+    }
+
+    /// Size of the decompressed image data: a filter byte plus the packed pixels of every row, per Adam7 pass when interlaced.
+    /// `None` when the size does not fit in a `usize`, which a valid IHDR at the largest dimensions can claim
+    pub fn filtered_size(&self) -> Option<usize> {
+        let bpp = self.bits_per_pixel() as u64;
+        let size = |w: u64, h: u64| if w == 0 { Some(0) } else { h.checked_mul(w.checked_mul(bpp)?.div_ceil(8).checked_add(1)?) };
+        let (width, height) = (self.width as u64, self.height as u64);
+        let total = if self.is_interlaced() {
+            // (x start, y start, x step, y step) of each Adam7 pass
+            const PASSES: [(u64, u64, u64, u64); 7] = [(0, 0, 8, 8), (4, 0, 8, 8), (0, 4, 4, 8), (2, 0, 4, 4), (0, 2, 2, 4), (1, 0, 2, 2), (0, 1, 1, 2)];
+            PASSES.iter().try_fold(0u64, |total, (x, y, dx, dy)| {
+                total.checked_add(size(width.saturating_sub(*x).div_ceil(*dx), height.saturating_sub(*y).div_ceil(*dy))?)
+            })
+        } else {
+            size(width, height)
+        };
+        total.and_then(|total| usize::try_from(total).ok())
+    }
+}
+
+impl TryFrom<&[u8]> for Ihdr {
+    type Error = IhdrError;
+
+    fn try_from(data: &[u8]) -> Result<Self, Self::Error> {
+        let [w0, w1, w2, w3, h0, h1, h2, h3, bit_depth, color_type, compression_method, filter_method, interlace_method] =
+            <[u8; 13]>::try_from(data).map_err(|_| IhdrError::InvalidLength(data.len()))?;
+        let (width, height) = (u32::from_be_bytes([w0, w1, w2, w3]), u32::from_be_bytes([h0, h1, h2, h3]));
+        if !(1..1 << 31).contains(&width) || !(1..1 << 31).contains(&height) {
+            return Err(IhdrError::InvalidDimensions(width, height));
+        }
+        let allowed_depths: &[u8] = match color_type {
+            0 => &[1, 2, 4, 8, 16],
+            3 => &[1, 2, 4, 8],
+            2 | 4 | 6 => &[8, 16],
+            _ => return Err(IhdrError::InvalidColorType(color_type)),
+        };
+        if !allowed_depths.contains(&bit_depth) {
+            return Err(IhdrError::InvalidBitDepth(bit_depth, color_type));
+        }
+        if compression_method != 0 {
+            return Err(IhdrError::InvalidCompressionMethod(compression_method));
+        }
+        if filter_method != 0 {
+            return Err(IhdrError::InvalidFilterMethod(filter_method));
+        }
+        if interlace_method > 1 {
+            return Err(IhdrError::InvalidInterlaceMethod(interlace_method));
+        }
+        Ok(Ihdr { width, height, bit_depth, color_type, compression_method, filter_method, interlace_method })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ihdr_bytes(width: u32, height: u32, bit_depth: u8, color_type: u8, interlace: u8) -> Vec<u8> {
+        let mut data = width.to_be_bytes().to_vec();
+        data.extend(height.to_be_bytes());
+        data.extend([bit_depth, color_type, 0, 0, interlace]);
+        data
+    }
+
+    #[test]
+    fn test_parse_ihdr() {
+        let ihdr = Ihdr::try_from(ihdr_bytes(50, 40, 8, 6, 0).as_slice()).unwrap();
+        assert_eq!((ihdr.width, ihdr.height, ihdr.bit_depth, ihdr.color_type), (50, 40, 8, 6));
+        assert_eq!(ihdr.channels(), 4);
+        assert_eq!(ihdr.filtered_size(), Some(40 * (1 + 50 * 4)));
+
+        let ihdr = Ihdr::try_from(ihdr_bytes(8, 8, 1, 3, 1).as_slice()).unwrap();
+        assert!(ihdr.is_interlaced());
+        // The seven Adam7 passes over an 8x8 image have 15 rows, each a filter byte and one byte of 1-bit pixels
+        assert_eq!(ihdr.filtered_size(), Some(15 * 2));
+
+        // The largest legal image needs far more than 2^64 bytes
+        let max = (1 << 31) - 1;
+        for interlace in [0, 1] {
+            let ihdr = Ihdr::try_from(ihdr_bytes(max, max, 16, 6, interlace).as_slice()).unwrap();
+            assert_eq!(ihdr.filtered_size(), None);
+        }
+    }
+
+    #[test]
+    fn test_reject_illegal_combinations() {
+        let err = |data: Vec<u8>| Ihdr::try_from(data.as_slice()).unwrap_err();
+        assert!(matches!(err(ihdr_bytes(50, 50, 8, 6, 0)[..12].to_vec()), IhdrError::InvalidLength(12)));
+        assert!(matches!(err(ihdr_bytes(0, 50, 8, 6, 0)), IhdrError::InvalidDimensions(0, 50)));
+        assert!(matches!(err(ihdr_bytes(50, 1 << 31, 8, 6, 0)), IhdrError::InvalidDimensions(50, _)));
+        assert!(matches!(err(ihdr_bytes(50, 50, 8, 5, 0)), IhdrError::InvalidColorType(5)));
+        assert!(matches!(err(ihdr_bytes(50, 50, 16, 3, 0)), IhdrError::InvalidBitDepth(16, 3)));
+        assert!(matches!(err(ihdr_bytes(50, 50, 4, 2, 0)), IhdrError::InvalidBitDepth(4, 2)));
+        assert!(matches!(err(ihdr_bytes(50, 50, 8, 2, 2)), IhdrError::InvalidInterlaceMethod(2)));
+
+        let mut data = ihdr_bytes(50, 50, 8, 2, 0);
+        data[10] = 1;
+        assert!(matches!(err(data), IhdrError::InvalidCompressionMethod(1)));
+    }
+}
@@ -0,0 +1,301 @@
+use std::fmt::{Display, Formatter};
+
+use anyhow::bail;
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum JsonError {
+    #[error("Unexpected end of JSON input")]
+    UnexpectedEnd,
+    #[error("Unexpected character '{0}' at position {1}")]
+    UnexpectedChar(char, usize),
+    #[error("Invalid escape sequence at position {0}")]
+    InvalidEscape(usize),
+    #[error("Trailing characters after JSON value at position {0}")]
+    TrailingCharacters(usize),
+}
+
+/// A parsed JSON value, object keys keep their original order
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Null,
+    Bool(bool),
+    // Numbers are kept as their source text so they round-trip unchanged
+    Number(String),
+    String(String),
+    Array(Vec<Value>),
+    Object(Vec<(String, Value)>),
+}
+
+impl Value {
+    pub fn parse(s: &str) -> anyhow::Result<Value> {
+        let mut parser = Parser { chars: s.chars().collect(), pos: 0 };
+        let value = parser.parse_value()?;
+        parser.skip_whitespace();
+        if parser.pos != parser.chars.len() {
+            bail!(JsonError::TrailingCharacters(parser.pos));
+        }
+        Ok(value)
+    }
+
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        match self {
+            Value::Object(entries) => entries.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    /// Multi-line rendering indented by two spaces per level
+    pub fn pretty(&self) -> String {
+        let mut out = String::new();
+        self.write_pretty(&mut out, 0);
+        out
+    }
+
+    fn write_pretty(&self, out: &mut String, depth: usize) {
+        let indent = "  ".repeat(depth + 1);
+        match self {
+            Value::Array(items) if !items.is_empty() => {
+                out.push_str("[\n");
+                for (i, item) in items.iter().enumerate() {
+                    out.push_str(&indent);
+                    item.write_pretty(out, depth + 1);
+                    out.push_str(if i + 1 < items.len() { ",\n" } else { "\n" });
+                }
+                out.push_str(&"  ".repeat(depth));
+                out.push(']');
+            }
+            Value::Object(entries) if !entries.is_empty() => {
+                out.push_str("{\n");
+                for (i, (key, value)) in entries.iter().enumerate() {
+                    out.push_str(&indent);
+                    out.push_str(&quote(key));
+                    out.push_str(": ");
+                    value.write_pretty(out, depth + 1);
+                    out.push_str(if i + 1 < entries.len() { ",\n" } else { "\n" });
+                }
+                out.push_str(&"  ".repeat(depth));
+                out.push('}');
+            }
+            other => out.push_str(&other.to_string()),
+        }
+    }
+}
+
+impl Display for Value {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Value::Null => write!(f, "null"),
+            Value::Bool(b) => write!(f, "{}", b),
+            Value::Number(n) => write!(f, "{}", n),
+            Value::String(s) => write!(f, "{}", quote(s)),
+            Value::Array(items) => {
+                let items: Vec<String> = items.iter().map(|item| item.to_string()).collect();
+                write!(f, "[{}]", items.join(","))
+            }
+            Value::Object(entries) => {
+                let entries: Vec<String> = entries.iter()
+                    .map(|(key, value)| format!("{}:{}", quote(key), value))
+                    .collect();
+                write!(f, "{{{}}}", entries.join(","))
+            }
+        }
+    }
+}
+
+/// Renders `s` as a quoted JSON string literal
+pub fn quote(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for ch in s.chars() {
+        match ch {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+}
+
+impl Parser {
+    fn skip_whitespace(&mut self) {
+        while self.chars.get(self.pos).is_some_and(|c| c.is_ascii_whitespace()) {
+            self.pos += 1;
+        }
+    }
+
+    fn next(&mut self) -> anyhow::Result<char> {
+        let ch = *self.chars.get(self.pos).ok_or(JsonError::UnexpectedEnd)?;
+        self.pos += 1;
+        Ok(ch)
+    }
+
+    fn expect(&mut self, expected: char) -> anyhow::Result<()> {
+        let ch = self.next()?;
+        if ch != expected {
+            bail!(JsonError::UnexpectedChar(ch, self.pos - 1));
+        }
+        Ok(())
+    }
+
+    fn expect_word(&mut self, word: &str, value: Value) -> anyhow::Result<Value> {
+        for expected in word.chars() {
+            self.expect(expected)?;
+        }
+        Ok(value)
+    }
+
+    fn parse_value(&mut self) -> anyhow::Result<Value> {
+        self.skip_whitespace();
+        match *self.chars.get(self.pos).ok_or(JsonError::UnexpectedEnd)? {
+            'n' => self.expect_word("null", Value::Null),
+            't' => self.expect_word("true", Value::Bool(true)),
+            'f' => self.expect_word("false", Value::Bool(false)),
+            '"' => Ok(Value::String(self.parse_string()?)),
+            '[' => self.parse_array(),
+            '{' => self.parse_object(),
+            c if c == '-' || c.is_ascii_digit() => self.parse_number(),
+            c => bail!(JsonError::UnexpectedChar(c, self.pos)),
+        }
+    }
+
+    fn parse_number(&mut self) -> anyhow::Result<Value> {
+        let start = self.pos;
+        while self.chars.get(self.pos).is_some_and(|c| c.is_ascii_digit() || "+-.eE".contains(*c)) {
+            self.pos += 1;
+        }
+        let text: String = self.chars[start..self.pos].iter().collect();
+        if text.parse::<f64>().is_err() || text.ends_with('.') || text.starts_with("-.") {
+            bail!(JsonError::UnexpectedChar(self.chars[start], start));
+        }
+        Ok(Value::Number(text))
+    }
+
+    fn parse_string(&mut self) -> anyhow::Result<String> {
+        self.expect('"')?;
+        let mut out = String::new();
+        loop {
+            match self.next()? {
+                '"' => return Ok(out),
+                '\\' => {
+                    let escape_pos = self.pos - 1;
+                    match self.next()? {
+                        '"' => out.push('"'),
+                        '\\' => out.push('\\'),
+                        '/' => out.push('/'),
+                        'b' => out.push('\u{8}'),
+                        'f' => out.push('\u{c}'),
+                        'n' => out.push('\n'),
+                        'r' => out.push('\r'),
+                        't' => out.push('\t'),
+                        'u' => {
+                            let hex: String = (0..4).map(|_| self.next()).collect::<anyhow::Result<_>>()?;
+                            let ch = u32::from_str_radix(&hex, 16).ok()
+                                .and_then(char::from_u32)
+                                .ok_or(JsonError::InvalidEscape(escape_pos))?;
+                            out.push(ch);
+                        }
+                        _ => bail!(JsonError::InvalidEscape(escape_pos)),
+                    }
+                }
+                c => out.push(c),
+            }
+        }
+    }
+
+    fn parse_array(&mut self) -> anyhow::Result<Value> {
+        self.expect('[')?;
+        let mut items = Vec::new();
+        self.skip_whitespace();
+        if self.chars.get(self.pos) == Some(&']') {
+            self.pos += 1;
+            return Ok(Value::Array(items));
+        }
+        loop {
+            items.push(self.parse_value()?);
+            self.skip_whitespace();
+            match self.next()? {
+                ',' => continue,
+                ']' => return Ok(Value::Array(items)),
+                c => bail!(JsonError::UnexpectedChar(c, self.pos - 1)),
+            }
+        }
+    }
+
+    fn parse_object(&mut self) -> anyhow::Result<Value> {
+        self.expect('{')?;
+        let mut entries = Vec::new();
+        self.skip_whitespace();
+        if self.chars.get(self.pos) == Some(&'}') {
+            self.pos += 1;
+            return Ok(Value::Object(entries));
+        }
+        loop {
+            self.skip_whitespace();
+            let key = self.parse_string()?;
+            self.skip_whitespace();
+            self.expect(':')?;
+            entries.push((key, self.parse_value()?));
+            self.skip_whitespace();
+            match self.next()? {
+                ',' => continue,
+                '}' => return Ok(Value::Object(entries)),
+                c => bail!(JsonError::UnexpectedChar(c, self.pos - 1)),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_object() {
+        let value = Value::parse(r#"{"author": "x", "tags": [1, true, null]}"#).unwrap();
+        assert_eq!(value.get("author").and_then(Value::as_str), Some("x"));
+        assert_eq!(value.to_string(), r#"{"author":"x","tags":[1,true,null]}"#);
+    }
+
+    #[test]
+    fn test_parse_escapes() {
+        let value = Value::parse(r#""a\"b\u0041\n""#).unwrap();
+        assert_eq!(value, Value::String("a\"bA\n".to_string()));
+    }
+
+    #[test]
+    fn test_parse_invalid() {
+        assert!(Value::parse("{\"a\":}").is_err());
+        assert!(Value::parse("[1, 2").is_err());
+        assert!(Value::parse("{} x").is_err());
+        assert!(Value::parse("01.").is_err());
+    }
+
+    #[test]
+    fn test_pretty() {
+        let value = Value::parse(r#"{"a":[1,2],"b":{}}"#).unwrap();
+        assert_eq!(value.pretty(), "{\n  \"a\": [\n    1,\n    2\n  ],\n  \"b\": {}\n}");
+    }
+
+    #[test]
+    fn test_quote() {
+        assert_eq!(quote("a\"b\\c\n"), "\"a\\\"b\\\\c\\n\"");
+    }
+}
@@ -0,0 +1,37 @@
+//! Reading, editing and writing the chunks of PNG files, the library behind the `pngme` CLI.
+//!
+//! ```
+//! use std::str::FromStr;
+//!
+//! use pngme::{Chunk, ChunkType, Png};
+//!
+//! let mut png = Png::try_from(&include_bytes!("../fuzz/corpus/png_try_from/dice.png")[..])?;
+//! png.append_chunk_indexed(Chunk::new(ChunkType::from_str("ruSt")?, b"hidden".to_vec()));
+//!
+//! let png = Png::try_from(png.as_bytes().as_slice())?;
+//! assert_eq!(png.chunk_by_type("ruSt").unwrap().data(), b"hidden");
+//! assert!(png.validate().is_empty());
+//! # Ok::<(), anyhow::Error>(())
+//! ```
+
+pub mod base64;
+#[cfg(feature = "blake3")]
+pub mod blake3;
+pub mod chunk;
+pub mod chunk_type;
+pub mod hex;
+pub mod ihdr;
+pub mod json;
+#[cfg(all(feature = "mmap", unix))]
+pub mod mmap;
+pub mod png;
+#[cfg(feature = "sha256")]
+pub mod sha256;
+#[cfg(feature = "compression")]
+pub mod stego;
+#[cfg(feature = "compression")]
+pub mod zlib;
+
+pub use chunk::Chunk;
+pub use chunk_type::ChunkType;
+pub use png::Png;
@@ -0,0 +1,222 @@
+use std::io::{self, Write};
+
+use pngme::chunk::Chunk;
+use pngme::png::{ChunkChange, Png, PngDiff};
+
+use crate::cli::{PrintFormat, SortOrder};
+
+/// Orders the chunks for display, ties keep their file order unless `reverse` flips the whole view
+pub fn sort_chunks(chunks: &mut [&Chunk], order: SortOrder, reverse: bool) {
+    match order {
+        SortOrder::Index => {}
+        SortOrder::Type => chunks.sort_by(|a, b| a.chunk_type().cmp(b.chunk_type())),
+        SortOrder::Size => chunks.sort_by_key(|chunk| chunk.total_size()),
+    }
+    if reverse {
+        chunks.reverse();
+    }
+}
+
+/// Writes the chunks in the Print format, showing at most `limit` of them, `annotate` adds what each type means
+pub fn write_chunks<W: Write>(out: &mut W, chunks: &[&Chunk], limit: Option<usize>, format: PrintFormat, annotate: bool) -> io::Result<()> {
+    let shown = limit.unwrap_or(chunks.len()).min(chunks.len());
+    for (i, chunk) in chunks[..shown].iter().enumerate() {
+        let annotation = annotate.then(|| annotation(chunk));
+        match format {
+            PrintFormat::Text => {
+                writeln!(out, "{}", chunk)?;
+                if let Some(annotation) = annotation {
+                    writeln!(out, "{}", annotation)?;
+                }
+                writeln!(out, "-----------")?;
+            }
+            PrintFormat::CArray => {
+                if let Some(annotation) = annotation {
+                    writeln!(out, "/* {} */", annotation)?;
+                }
+                writeln!(out, "{}", c_array(&format!("{}_{}", chunk.chunk_type(), i), chunk.data()))?;
+            }
+        }
+    }
+    if shown < chunks.len() {
+        match format {
+            PrintFormat::Text => writeln!(out, "…and {} more", chunks.len() - shown)?,
+            PrintFormat::CArray => writeln!(out, "/* …and {} more */", chunks.len() - shown)?,
+        }
+    }
+    Ok(())
+}
+
+fn annotation(chunk: &Chunk) -> String {
+    format!("{}: {}", chunk.chunk_type(), chunk.chunk_type().description().unwrap_or("(custom/private)"))
+}
+
+/// `data` as a C array literal, wrapped at 12 bytes per line once it no longer fits on one
+pub fn c_array(name: &str, data: &[u8]) -> String {
+    let rows: Vec<String> = data
+        .chunks(12)
+        .map(|row| row.iter().map(|b| format!("0x{:02x}", b)).collect::<Vec<_>>().join(", "))
+        .collect();
+    if rows.len() > 1 {
+        format!("const unsigned char {}[] = {{\n    {}\n}};", name, rows.join(",\n    "))
+    } else {
+        format!("const unsigned char {}[] = {{ {} }};", name, rows.join(""))
+    }
+}
+
+/// Writes the chunks added (`+`), removed (`-`) and modified (`~`) in `diff`
+pub fn write_diff<W: Write>(out: &mut W, diff: &PngDiff) -> io::Result<()> {
+    for change in &diff.changes {
+        match change {
+            ChunkChange::Added { index, chunk_type, length } => writeln!(out, "+ {} {} ({} bytes)", index, chunk_type, length)?,
+            ChunkChange::Removed { index, chunk_type, length } => writeln!(out, "- {} {} ({} bytes)", index, chunk_type, length)?,
+            ChunkChange::Modified { index, chunk_type, before, after } => {
+                writeln!(out, "~ {} {} ({} -> {} bytes)", index, chunk_type, before, after)?
+            }
+        }
+    }
+    if diff.is_empty() {
+        writeln!(out, "No chunk changes")?;
+    }
+    Ok(())
+}
+
+/// Writes what a `--dry-run` would change, the chunk diff followed by the size before and after
+pub fn write_dry_run<W: Write>(out: &mut W, before: &Png, after: &Png) -> io::Result<()> {
+    write_diff(out, &before.diff_against(after))?;
+    writeln!(out, "Dry run, {} -> {} bytes, nothing was written", before.as_bytes().len(), after.as_bytes().len())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use pngme::chunk_type::ChunkType;
+    use super::*;
+
+    fn testing_chunks() -> Vec<Chunk> {
+        ["FrSt", "miDl", "thRd", "LASt"]
+            .iter()
+            .map(|t| Chunk::new(ChunkType::from_str(t).unwrap(), t.as_bytes().to_vec()))
+            .collect()
+    }
+
+    #[test]
+    fn test_write_chunks_with_limit() {
+        let chunks = testing_chunks();
+        let chunks: Vec<&Chunk> = chunks.iter().collect();
+        let mut out = Vec::new();
+        write_chunks(&mut out, &chunks, Some(2), PrintFormat::Text, false).unwrap();
+        let out = String::from_utf8(out).unwrap();
+
+        assert_eq!(out.matches("-----------").count(), 2);
+        assert!(out.contains("Chunk type: FrSt"));
+        assert!(out.contains("Chunk type: miDl"));
+        assert!(!out.contains("Chunk type: thRd"));
+        assert!(out.ends_with("…and 2 more\n"));
+    }
+
+    #[test]
+    fn test_write_chunks_without_limit() {
+        let chunks = testing_chunks();
+        let chunks: Vec<&Chunk> = chunks.iter().collect();
+        let mut out = Vec::new();
+        write_chunks(&mut out, &chunks, None, PrintFormat::Text, false).unwrap();
+        let out = String::from_utf8(out).unwrap();
+
+        assert_eq!(out.matches("-----------").count(), 4);
+        assert!(!out.contains("more"));
+    }
+
+    #[test]
+    fn test_write_chunks_annotated() {
+        let png = Png::try_from(&include_bytes!("../fuzz/corpus/png_try_from/dice.png")[..]).unwrap();
+        let chunks: Vec<&Chunk> = png.chunks().iter().collect();
+        let mut out = Vec::new();
+        write_chunks(&mut out, &chunks, None, PrintFormat::Text, true).unwrap();
+        let out = String::from_utf8(out).unwrap();
+
+        assert!(out.contains("\nIHDR: image header\n-----------"));
+        assert!(out.contains("\nRuSt: (custom/private)\n"));
+    }
+
+    #[test]
+    fn test_c_array() {
+        assert_eq!(c_array("data", &[0x89, 0x50, 0x00, 0xff]), "const unsigned char data[] = { 0x89, 0x50, 0x00, 0xff };");
+        assert_eq!(
+            c_array("data", &[0; 13]),
+            "const unsigned char data[] = {\n    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,\n    0x00\n};"
+        );
+
+        let chunks = testing_chunks();
+        let chunks: Vec<&Chunk> = chunks.iter().collect();
+        let mut out = Vec::new();
+        write_chunks(&mut out, &chunks, Some(1), PrintFormat::CArray, false).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), "const unsigned char FrSt_0[] = { 0x46, 0x72, 0x53, 0x74 };\n/* …and 3 more */\n");
+    }
+
+    #[test]
+    fn test_sort_chunks() {
+        let chunks = [
+            Chunk::new(ChunkType::from_str("miDl").unwrap(), vec![0; 10]),
+            Chunk::new(ChunkType::from_str("FrSt").unwrap(), vec![0; 30]),
+            Chunk::new(ChunkType::from_str("LASt").unwrap(), vec![0; 20]),
+        ];
+        let types = |chunks: &[&Chunk]| chunks.iter().map(|chunk| chunk.chunk_type().to_string()).collect::<Vec<_>>();
+
+        let mut view: Vec<&Chunk> = chunks.iter().collect();
+        sort_chunks(&mut view, SortOrder::Size, false);
+        assert_eq!(types(&view), ["miDl", "LASt", "FrSt"]);
+        sort_chunks(&mut view, SortOrder::Type, false);
+        assert_eq!(types(&view), ["FrSt", "LASt", "miDl"]);
+
+        let mut view: Vec<&Chunk> = chunks.iter().collect();
+        sort_chunks(&mut view, SortOrder::Index, false);
+        assert_eq!(types(&view), ["miDl", "FrSt", "LASt"]);
+
+        sort_chunks(&mut view, SortOrder::Index, true);
+        assert_eq!(types(&view), ["LASt", "FrSt", "miDl"]);
+        sort_chunks(&mut view, SortOrder::Size, true);
+        assert_eq!(types(&view), ["FrSt", "LASt", "miDl"]);
+    }
+
+    fn diff(before: &[Chunk], after: &[Chunk]) -> String {
+        let mut out = Vec::new();
+        let diff = Png::from_chunks(before.to_vec()).diff_against(&Png::from_chunks(after.to_vec()));
+        write_diff(&mut out, &diff).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn test_write_diff_added_chunk() {
+        let before = testing_chunks();
+        let mut after = before.clone();
+        after.insert(3, Chunk::new(ChunkType::from_str("ruSt").unwrap(), b"hidden".to_vec()));
+
+        assert_eq!(diff(&before, &after), "+ 3 ruSt (6 bytes)\n");
+        assert_eq!(diff(&before, &before), "No chunk changes\n");
+    }
+
+    #[test]
+    fn test_write_dry_run() {
+        let before = Png::from_chunks(testing_chunks());
+        let mut after = before.clone();
+        let idx = after.append_chunk_indexed(Chunk::new(ChunkType::from_str("ruSt").unwrap(), b"hidden".to_vec()));
+        let mut out = Vec::new();
+        write_dry_run(&mut out, &before, &after).unwrap();
+        let out = String::from_utf8(out).unwrap();
+
+        assert_eq!(out, format!("+ {} ruSt (6 bytes)\nDry run, {} -> {} bytes, nothing was written\n",
+                                idx, before.as_bytes().len(), after.as_bytes().len()));
+    }
+
+    #[test]
+    fn test_write_diff_removed_and_modified() {
+        let before = testing_chunks();
+        let mut after = before.clone();
+        after.remove(0);
+        after[1] = Chunk::new(ChunkType::from_str("thRd").unwrap(), b"changed".to_vec());
+
+        assert_eq!(diff(&before, &after), "- 0 FrSt (4 bytes)\n~ 2 thRd (4 -> 7 bytes)\n");
+    }
+}
@@ -1,57 +1,1123 @@
+use std::borrow::Cow;
 use std::fs;
+use std::io::{self, IsTerminal, Write};
+use std::path::Path;
+use std::process::ExitCode;
+use std::str::FromStr;
 
+use anyhow::{bail, Context};
 use clap::Parser;
+use crc::{Crc, CRC_32_ISO_HDLC};
 
-use crate::chunk::Chunk;
-use crate::cli::{Cli, Commands};
-use crate::png::Png;
+use pngme::base64;
+use pngme::chunk::{Chunk, InternationalText};
+use pngme::chunk_type::{ChunkType, ChunkTypeError};
+use pngme::hex;
+use pngme::json::Value;
+use pngme::png::{Png, PngError};
 
+use crate::cli::{ChecksumAlgorithm, Cli, Commands, EmbedMode, ErrorFormat, InputFormat, PayloadCompression};
+use crate::report::FileContext;
+
+mod batch;
+mod bench;
+mod check;
 mod cli;
-mod chunk;
-mod chunk_type;
-mod png;
+mod listing;
+mod prompt;
+mod report;
+mod stdio;
 
-fn main() -> anyhow::Result<()> {
+fn main() -> ExitCode {
     let cli = Cli::parse();
+    let file_path = cli.command.file_path().map(str::to_string);
+    let error_format = cli.error_format;
+
+    match run(cli) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            match error_format {
+                ErrorFormat::Text => eprintln!("Error: {:?}", err),
+                ErrorFormat::Json => eprintln!("{}", report::error_json(&err, file_path.as_deref())),
+            }
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// How input files are decoded and parsed, shared by every command reading a PNG
+#[derive(Clone, Copy)]
+struct ReadOptions {
+    // Chunks with mismatching CRCs are kept as-is so they are written back unchanged
+    lenient: bool,
+    // With `lenient`, a damaged signature and truncated chunks are accepted too, only commands inspecting or repairing files set it
+    recover: bool,
+    no_signature: bool,
+    input_format: InputFormat,
+}
+
+fn read_png(file_path: &str, options: ReadOptions) -> anyhow::Result<Png> {
+    // Binary PNGs are parsed straight from the mapped file, encoded inputs still need decoding onto the heap
+    #[cfg(all(feature = "mmap", unix))]
+    if !stdio::is_stdio(file_path) && matches!(options.input_format, InputFormat::Png | InputFormat::Auto) {
+        // SAFETY: the map only lives while this call parses it and pngme never writes to a file it is reading,
+        // the `mmap` feature documents that other processes must leave the input alone meanwhile
+        let map = unsafe { pngme::mmap::Mmap::open(file_path) }?;
+        if options.input_format == InputFormat::Png || is_binary(&map) {
+            return parse_png(&map, options);
+        }
+    }
+    let content = decode_input(stdio::read_input(file_path)?, options.input_format)?;
+    parse_png(&content, options)
+}
+
+fn parse_png(content: &[u8], options: ReadOptions) -> anyhow::Result<Png> {
+    let png = if options.no_signature {
+        Png::from_chunk_stream(content, options.lenient)?
+    } else if options.lenient {
+        Png::from_bytes_lenient(content)?
+    } else {
+        Png::try_from(content)?
+    };
+    // Keeping stored CRCs must not quietly rewrite a signature or the length of a truncated chunk
+    if options.lenient && !options.recover {
+        if !png.has_valid_signature() {
+            bail!(PngError::InvalidHeaderContent);
+        }
+        if let Some(idx) = png.position_of(Chunk::is_truncated) {
+            bail!("Chunk {} ({}) at byte offset {} is truncated, --no-crc-recompute only keeps stored CRCs and does not recover damaged files",
+                  idx, png.chunks()[idx].chunk_type(), png.byte_offset_of(idx).unwrap());
+        }
+    }
+    Ok(png)
+}
+
+// Fails early with a clear message instead of the opaque OS error `fs::write` gives for directories
+fn ensure_not_directory(path: &str) -> anyhow::Result<()> {
+    if Path::new(path).is_dir() {
+        bail!("output path is a directory: {}", path);
+    }
+    Ok(())
+}
+
+/// How output files are written, shared by every command rewriting a PNG
+#[derive(Clone, Copy)]
+struct WriteOptions {
+    // The output gets the modification time the input file had before it was rewritten
+    preserve_mtime: bool,
+    // An existing output file is copied to `<output>.bak` before it is replaced
+    backup: bool,
+}
+
+// Reads a file besides the one the command works on, errors name it instead of the command's file
+fn read_other(path: &str) -> anyhow::Result<Vec<u8>> {
+    stdio::read_input(path).with_context(|| FileContext(path.to_string()))
+}
+
+// Writes an output file, errors name it instead of the command's input file
+fn write_other(path: &str, bytes: &[u8]) -> anyhow::Result<()> {
+    ensure_not_directory(path)
+        .and_then(|()| Ok(stdio::write_output(path, bytes)?))
+        .with_context(|| FileContext(path.to_string()))
+}
+
+// Writes a temporary file next to `output_file` and renames it into place, so a failed write never destroys the original.
+// `-` streams the PNG to stdout instead
+fn write_png(input_file: &str, output_file: &str, png: &Png, options: WriteOptions) -> anyhow::Result<()> {
+    if stdio::is_stdio(output_file) {
+        return write_other(output_file, &png.as_bytes());
+    }
+    replace_file(input_file, output_file, options, |path| png.write_atomically(path))
+}
 
+// Runs `write` to replace `output_file` with the backup and mtime handling the options ask for
+fn replace_file(input_file: &str, output_file: &str, options: WriteOptions, write: impl FnOnce(&Path) -> anyhow::Result<()>) -> anyhow::Result<()> {
+    let replaced = || -> anyhow::Result<()> {
+        ensure_not_directory(output_file)?;
+        let mtime = if options.preserve_mtime && !stdio::is_stdio(input_file) { Some(fs::metadata(input_file)?.modified()?) } else { None };
+        let output = Path::new(output_file);
+        if options.backup && output.exists() {
+            fs::copy(output, format!("{}.bak", output_file))?;
+        }
+        write(output)?;
+        if let Some(mtime) = mtime {
+            fs::File::options().write(true).open(output)?.set_modified(mtime)?;
+        }
+        Ok(())
+    };
+    replaced().with_context(|| FileContext(output_file.to_string()))
+}
+
+// `Auto` only decodes printable text, anything else including raw PNGs and chunk streams is used as-is
+fn is_binary(content: &[u8]) -> bool {
+    content.starts_with(&Png::STANDARD_HEADER) || !content.iter().all(|b| b.is_ascii_graphic() || b.is_ascii_whitespace())
+}
+
+fn decode_input(content: Vec<u8>, format: InputFormat) -> anyhow::Result<Vec<u8>> {
+    let text = || std::str::from_utf8(&content).map_err(anyhow::Error::from);
+    match format {
+        InputFormat::Png => Ok(content),
+        InputFormat::Hex => hex::decode(text()?),
+        InputFormat::Base64 => base64::decode(text()?),
+        InputFormat::Auto if is_binary(&content) => Ok(content),
+        InputFormat::Auto if content.iter().all(|b| b.is_ascii_hexdigit() || b.is_ascii_whitespace()) => hex::decode(text()?),
+        InputFormat::Auto => base64::decode(text()?),
+    }
+}
+
+fn run(cli: Cli) -> anyhow::Result<()> {
+    let read = ReadOptions { lenient: cli.no_crc_recompute, recover: false, no_signature: cli.no_signature, input_format: cli.input_format };
+    let read_lenient = ReadOptions { lenient: true, recover: true, ..read };
+    let write = WriteOptions { preserve_mtime: cli.preserve_mtime, backup: cli.backup };
+    let error_format = cli.error_format;
     match cli.command {
-        Commands::Encode { file_path, chunk_type, message, output_file } => {
-            let content = fs::read(&file_path)?;
-            let mut png = Png::try_from(content.as_slice())?;
-            let chunk = Chunk::new(chunk_type, message.as_bytes().to_vec());
-            png.append_chunk(chunk);
-            let out_file = output_file.unwrap_or(file_path);
-            fs::write(out_file, png.as_bytes())?
-        }
-        Commands::Decode { file_path, chunk_type } => {
+        Commands::Encode { file_path, chunk_type, mode, chunk_type_file, message, text, compress, hmac_key, text_keyword, itxt, language, translated_keyword, after_iend, output_file, yes, allow_critical, dry_run, repeat } => {
+            // `--text KEY=VALUE` is the same tEXt chunk as `--text-keyword KEY --message VALUE`
+            let (text_keyword, message) = match text {
+                Some((keyword, text)) => (Some(keyword), text),
+                None => (text_keyword, message.unwrap_or_default()),
+            };
+            let international = itxt.then(|| (language.as_deref().unwrap_or_default(), translated_keyword.as_deref().unwrap_or_default()));
+            let text_chunk = text_keyword.as_deref().map(|keyword| text_chunk(keyword, &message, compress, international)).transpose()?;
+            check_mode_chunk_type(mode, chunk_type.is_some() || chunk_type_file.is_some())?;
+            let chunk_type = resolve_chunk_type(chunk_type, chunk_type_file.as_deref())?;
+            if let Some(chunk_type) = &chunk_type {
+                check_critical(chunk_type, allow_critical)?;
+            }
+            let mut png = read_png(&file_path, read)?;
+            let out_file = output_file.as_deref().unwrap_or(&file_path);
+            let before = dry_run.then(|| png.clone());
+            let mut added_at = None;
+            for _ in 0..repeat.unwrap_or(1) {
+                added_at = match mode {
+                    #[cfg(feature = "compression")]
+                    Some(EmbedMode::Lsb) => {
+                        pngme::stego::embed(&mut png, &tag_message(pack_message(message.as_bytes(), compress), hmac_key.as_deref())?)?;
+                        None
+                    }
+                    Some(EmbedMode::Chunk) | None => {
+                        let payload = tag_message(pack_message(message.as_bytes(), compress), hmac_key.as_deref())?;
+                        encode_message(&mut png, &message, Some(&payload), text_chunk.clone(), chunk_type.as_ref(), after_iend)?
+                    }
+                };
+            }
+            finish_encode(&mut stdio::status_out(out_file), before.as_ref(), &png, added_at, repeat.is_some(), || {
+                // Only prompt when overwriting the input interactively, scripts keep working unattended
+                if output_file.is_none() && !yes && !stdio::is_stdio(&file_path) && io::stdin().is_terminal()
+                    && !prompt::confirm_overwrite(&file_path, &mut io::stdin().lock(), &mut io::stderr())? {
+                    println!("Aborted, {} was left unchanged", file_path);
+                    return Ok(false);
+                }
+                write_png(&file_path, out_file, &png, write)?;
+                Ok(true)
+            })?;
+        }
+        #[cfg(feature = "compression")]
+        Commands::Decode { file_path, chunk_type, chunk_type_file, mode: Some(EmbedMode::Lsb), lossy, verify_key, .. } => {
+            check_mode_chunk_type(Some(EmbedMode::Lsb), chunk_type.is_some() || chunk_type_file.is_some())?;
+            let payload = pngme::stego::extract(&read_png(&file_path, read)?)?;
+            println!("Message: {}", payload_message(&payload, lossy, verify_key.as_deref())?);
+        }
+        Commands::Decode { file_path, list_text: true, .. } => {
+            let png = read_png(&file_path, read)?;
+            let mut found = false;
+            for chunk in png.chunks() {
+                #[cfg(feature = "compression")]
+                let entry = chunk.text_entry().or(chunk.ztxt_entry()?);
+                #[cfg(not(feature = "compression"))]
+                let entry = chunk.text_entry();
+                if let Some((keyword, text)) = entry {
+                    found = true;
+                    println!("Keyword: {}\nText: {}", keyword, text);
+                } else if let Some(entry) = chunk.itxt_entry()? {
+                    found = true;
+                    println!("Keyword: {}\nLanguage: {}\nTranslated keyword: {}\nText: {}",
+                             entry.keyword, entry.language, entry.translated_keyword, entry.text);
+                }
+            }
+            if !found {
+                println!("[WARN] - No text chunks found in: {}", file_path);
+            }
+        }
+        Commands::Decode { file_path, after_iend: true, .. } => {
+            let png = read_png(&file_path, read)?;
+            match split_trailing_message(png.trailing_bytes()) {
+                (_, Some(message)) => println!("Message: {}", String::from_utf8_lossy(message)),
+                (_, None) => println!("[WARN] - No message found after IEND in: {}", file_path),
+            }
+        }
+        Commands::Decode { file_path, chunk_type: _, text_keyword: Some(keyword), allow_types, .. } => {
+            let png = read_png(&file_path, read)?;
+            if let Some(text) = text_by_keyword(&png, &keyword, allow_types.as_deref())? {
+                println!("Message: {}", text);
+            } else {
+                println!("[WARN] - No message found for keyword: {}", keyword);
+            }
+        }
+        Commands::Decode { file_path, chunk_type, mode, chunk_type_file, text_keyword: None, index, allow_types, lossy, verify_key, .. } => {
+            check_mode_chunk_type(mode, chunk_type.is_some() || chunk_type_file.is_some())?;
+            let chunk_type = resolve_chunk_type(chunk_type, chunk_type_file.as_deref())?;
+            let chunk_type = chunk_type.ok_or_else(|| anyhow::anyhow!("Either a chunk type or a text keyword is required"))?;
+            check_allowed(&chunk_type, allow_types.as_deref())?;
             let chunk_type_bytes = chunk_type.bytes();
             let chunk_str = std::str::from_utf8(&chunk_type_bytes).unwrap();
-            let content = fs::read(&file_path)?;
-            let png = Png::try_from(content.as_slice())?;
-            let chunk_with_message = png.chunk_by_type(chunk_str);
+            let png = read_png(&file_path, read)?;
+            let chunk_with_message = png.chunk_by_type_at(chunk_str, index);
             if let Some(message) = chunk_with_message {
-                println!("Message: {}", message.data_as_string()?);
+                println!("Message: {}", payload_message(message.data(), lossy, verify_key.as_deref())?);
             } else {
                 println!("[WARN] - No message found for chunk: {}", chunk_str);
             }
         }
-        Commands::Remove { file_path, chunk_type } => {
+        Commands::EncodeFile { file_path, payload_file, chunk_type, output_file, piece_size, allow_critical } => {
+            check_critical(&chunk_type, allow_critical)?;
+            let mut png = read_png(&file_path, read)?;
+            let added = png.append_split(&chunk_type.to_string(), &read_other(&payload_file)?, piece_size)?;
+            let out_file = output_file.as_deref().unwrap_or(&file_path);
+            write_png(&file_path, out_file, &png, write)?;
+            stdio::status(out_file, format!("Embedded {} in {} {} chunk(s), wrote: {}", payload_file, added, chunk_type, out_file));
+        }
+        Commands::DecodeFile { file_path, chunk_type, output_file } => {
+            let png = read_png(&file_path, read)?;
+            let payload = png.join_split(&chunk_type.to_string())?;
+            write_other(&output_file, &payload)?;
+            stdio::status(&output_file, format!("Extracted {} bytes, wrote: {}", payload.len(), output_file));
+        }
+        Commands::Remove { file_path, chunk_type, dry_run } => {
             let chunk_type_bytes = chunk_type.bytes();
             let chunk_str = std::str::from_utf8(&chunk_type_bytes).unwrap();
-            let content = fs::read(&file_path)?;
-            let mut png = Png::try_from(content.as_slice())?;
+            let mut png = read_png(&file_path, read)?;
+            let before = dry_run.then(|| png.clone());
             let chunk = png.remove_chunk(chunk_str)?;
-            fs::write(file_path, png.as_bytes())?;
-            println!("Removed message: {}", chunk.data_as_string()?);
+            if let Some(before) = before {
+                return print_dry_run(&before, &png);
+            }
+            write_png(&file_path, &file_path, &png, write)?;
+            stdio::status(&file_path, format!("Removed message: {}", chunk_message(&chunk, false)?));
+        }
+        Commands::Expect { file_path, chunk_type, value } => {
+            let png = read_png(&file_path, read)?;
+            expect_message(&png, &chunk_type, &value)?;
+            println!("Message in {} matches the expected value", chunk_type);
+        }
+        Commands::Diff { file_path, other } => {
+            let png = read_png(&file_path, read_lenient)?;
+            let other_png = read_png(&other, read_lenient).with_context(|| FileContext(other.clone()))?;
+            listing::write_diff(&mut io::stdout().lock(), &png.diff_against(&other_png))?;
+        }
+        Commands::Print { file_path, type_prefix, limit, sort, reverse, format, annotate } => {
+            let png = read_png(&file_path, read)?;
+            let mut chunks = match type_prefix {
+                Some(prefix) => png.chunks_with_type_prefix(&prefix),
+                None => png.chunks().iter().collect(),
+            };
+            listing::sort_chunks(&mut chunks, sort, reverse);
+            listing::write_chunks(&mut io::stdout().lock(), &chunks, limit, format, annotate)?;
+        }
+        Commands::Extract { file_path, output_dir, chunk_type } => {
+            let png = read_png(&file_path, read)?;
+            let chunk_type = chunk_type.map(|chunk_type| chunk_type.to_string());
+            let written = png.extract_to_files(Path::new(&output_dir), chunk_type.as_deref())?;
+            written.iter().for_each(|path| println!("Wrote: {}", path.display()));
+        }
+        Commands::IsPng { file_path } => {
+            let is_png = if stdio::is_stdio(&file_path) {
+                Png::peek_signature(&mut io::stdin().lock())?
+            } else {
+                Png::peek_signature(&mut fs::File::open(&file_path)?)?
+            };
+            if !is_png {
+                bail!("Not a PNG file: {}", file_path);
+            }
+            println!("PNG file: {}", file_path);
+        }
+        Commands::Checksum { file_path, algorithm } => {
+            let content = stdio::read_input(&file_path)?;
+            println!("{}  {}", file_digest(&content, algorithm), file_path);
+        }
+        Commands::Verify { file_path, fail_fast } => {
+            batch::run_all(&file_path, fail_fast, error_format, &mut io::stderr(), |file_path| {
+                let png = read_png(file_path, read_lenient)?;
+                let problems = png.validate();
+                // The heuristic needs a readable IHDR and IDAT stream, structural problems already cover the other cases
+                #[cfg(feature = "compression")]
+                if let Ok(Some(warning)) = png.validate_ihdr_matches_data() {
+                    println!("[WARN] - {}", warning);
+                }
+                if problems.is_empty() {
+                    println!("No problems found in: {}", file_path);
+                } else {
+                    problems.iter().for_each(|problem| println!("{}", problem));
+                    bail!("{} problem(s) found in: {}", problems.len(), file_path);
+                }
+                Ok(())
+            })?;
+        }
+        Commands::Validate { file_path } => {
+            let png = read_png(&file_path, read_lenient)?;
+            let errors = check::write_validation(&mut io::stdout().lock(), &png)?;
+            if errors > 0 {
+                bail!("{} error(s) found in: {}", errors, file_path);
+            }
+        }
+        Commands::Check { file_path } => {
+            let png = read_png(&file_path, read_lenient)?;
+            check::write_summary(&mut io::stdout().lock(), &png)?;
+        }
+        Commands::Capacity { file_path } => {
+            let png = read_png(&file_path, read)?;
+            check::write_capacity(&mut io::stdout().lock(), &png)?;
+        }
+        Commands::Scan { file_path } => {
+            let png = read_png(&file_path, read_lenient)?;
+            check::write_scan(&mut io::stdout().lock(), &png)?;
+        }
+        Commands::AuditFlags { file_path } => {
+            let png = read_png(&file_path, read_lenient)?;
+            check::write_flag_audit(&mut io::stdout().lock(), &png)?;
+        }
+        Commands::WhatIfStrip { file_path, drop_unsafe_unknown, drop_all_ancillary } => {
+            let png = read_png(&file_path, read_lenient)?;
+            check::write_strip_preview(&mut io::stdout().lock(), &png, drop_unsafe_unknown, drop_all_ancillary)?;
+        }
+        Commands::Strip { file_path, keep, output_file } => {
+            let png = read_png(&file_path, read)?;
+            let out_file = output_file.as_deref().unwrap_or(&file_path);
+            if stdio::is_stdio(out_file) {
+                write_png(&file_path, out_file, &png.stripped(&keep), write)?;
+            } else {
+                replace_file(&file_path, out_file, write, |path| png.write_stripped(path, &keep))?;
+            }
+            stdio::status(out_file, format!("Stripped ancillary chunks, wrote: {}", out_file));
+        }
+        Commands::StripText { file_path, output_file } => {
+            let mut png = read_png(&file_path, read)?;
+            let removed = png.strip_text();
+            let out_file = output_file.as_deref().unwrap_or(&file_path);
+            write_png(&file_path, out_file, &png, write)?;
+            stdio::status(out_file, format!("Removed {} text chunk(s), wrote: {}", removed, out_file));
+        }
+        Commands::Normalize { file_path, output_file } => {
+            let mut png = read_png(&file_path, read_lenient)?;
+            png.canonicalize();
+            png.recompute_crcs();
+            let out_file = output_file.as_deref().unwrap_or(&file_path);
+            write_png(&file_path, out_file, &png, write)?;
+            stdio::status(out_file, format!("Normalized {} chunk(s), wrote: {}", png.chunks().len(), out_file));
+        }
+        Commands::Sanitize { file_path, output_file, keep_trailing } => {
+            let mut png = read_png(&file_path, read)?;
+            let before = png.as_bytes().len();
+            png.sanitize_for_web(keep_trailing);
+            let out_file = output_file.as_deref().unwrap_or(&file_path);
+            write_png(&file_path, out_file, &png, write)?;
+            stdio::status(out_file, format!("Saved {} bytes, wrote: {}", before - png.as_bytes().len(), out_file));
+        }
+        Commands::SetMeta { file_path, json, output_file, dry_run } => {
+            let metadata = Value::parse(&json)?;
+            if !matches!(metadata, Value::Object(_)) {
+                bail!("Metadata must be a JSON object");
+            }
+            let mut png = read_png(&file_path, read)?;
+            let before = dry_run.then(|| png.clone());
+            png.set_metadata(&metadata)?;
+            if let Some(before) = before {
+                return print_dry_run(&before, &png);
+            }
+            let out_file = output_file.as_deref().unwrap_or(&file_path);
+            write_png(&file_path, out_file, &png, write)?
+        }
+        Commands::GetMeta { file_path } => {
+            let png = read_png(&file_path, read)?;
+            match png.metadata()? {
+                Some(metadata) => println!("{}", metadata.pretty()),
+                None => println!("[WARN] - No metadata found in: {}", file_path),
+            }
+        }
+        Commands::Copy { source, source_type, dest, dest_type, output_file } => {
+            let source_png = read_png(&source, read)?;
+            let mut dest_png = read_png(&dest, read).with_context(|| FileContext(dest.clone()))?;
+            copy_chunk(&source_png, &source_type, &mut dest_png, dest_type)?;
+            let out_file = output_file.as_deref().unwrap_or(&dest);
+            write_png(&dest, out_file, &dest_png, write)?;
+            stdio::status(out_file, format!("Copied {} from {} into {}", source_type, source, out_file));
+        }
+        #[cfg(feature = "compression")]
+        Commands::Hide { cover_png, payload_file, chunk_type, output_file, integrity } => {
+            let mut png = read_png(&cover_png, read)?;
+            let added = png.hide_payload(&chunk_type.to_string(), &read_other(&payload_file)?, integrity)?;
+            let out_file = output_file.as_deref().unwrap_or(&cover_png);
+            write_png(&cover_png, out_file, &png, write)?;
+            stdio::status(out_file, format!("Hid {} in {} {} chunk(s), wrote: {}", payload_file, added, chunk_type, out_file));
+        }
+        #[cfg(feature = "compression")]
+        Commands::Reveal { file_path, chunk_type, output_file } => {
+            let png = read_png(&file_path, read)?;
+            let payload = png.reveal_payload(&chunk_type.to_string())?;
+            write_other(&output_file, &payload)?;
+            stdio::status(&output_file, format!("Revealed {} bytes, wrote: {}", payload.len(), output_file));
+        }
+        Commands::Bench { file_path, iterations } => {
+            let content = stdio::read_input(&file_path)?;
+            bench::write_throughput(&mut io::stdout().lock(), &content, iterations)?;
+        }
+        Commands::MakeChunk { chunk_type, input_file, output_file } => {
+            let chunk = make_chunk(chunk_type, &input_file, &output_file)?;
+            stdio::status(&output_file, format!("Wrote {} chunk of {} bytes to: {}", chunk.chunk_type(), chunk.as_bytes().len(), output_file));
+        }
+    }
+    Ok(())
+}
+
+// The chunk type given on the command line, or the 4 bytes stored in `chunk_type_file`
+fn resolve_chunk_type(chunk_type: Option<ChunkType>, chunk_type_file: Option<&str>) -> anyhow::Result<Option<ChunkType>> {
+    let Some(path) = chunk_type_file else {
+        return Ok(chunk_type);
+    };
+    let content = read_other(path)?;
+    // Tolerate the newline `echo` leaves, anything else besides the 4 type bytes is a mistake
+    let trimmed = content.strip_suffix(b"\r\n").or(content.strip_suffix(b"\n")).unwrap_or(&content);
+    let chunk_type = <[u8; 4]>::try_from(trimmed)
+        .map_err(|_| anyhow::Error::from(ChunkTypeError::WrongStringByteLength(trimmed.len())))
+        .and_then(ChunkType::try_from)
+        .with_context(|| FileContext(path.to_string()))?;
+    Ok(Some(chunk_type))
+}
+
+fn file_digest(content: &[u8], algorithm: ChecksumAlgorithm) -> String {
+    match algorithm {
+        ChecksumAlgorithm::Crc32 => hex::encode(&Crc::<u32>::new(&CRC_32_ISO_HDLC).checksum(content).to_be_bytes()),
+        #[cfg(feature = "sha256")]
+        ChecksumAlgorithm::Sha256 => hex::encode(&pngme::sha256::digest(content)),
+        #[cfg(feature = "blake3")]
+        ChecksumAlgorithm::Blake3 => hex::encode(&pngme::blake3::digest(content)),
+    }
+}
+
+// Ends Encode: a dry run only shows the diff, otherwise `write` stores the image and, once it has, where the message went is reported
+fn finish_encode<W: Write>(out: &mut W, before: Option<&Png>, png: &Png, added_at: Option<usize>, repeated: bool,
+                           write: impl FnOnce() -> anyhow::Result<bool>) -> anyhow::Result<()> {
+    if let Some(before) = before {
+        return Ok(listing::write_dry_run(out, before, png)?);
+    }
+    if !write()? {
+        return Ok(());
+    }
+    if repeated {
+        writeln!(out, "Chunks: {}, file size: {} bytes", png.chunks().len(), png.as_bytes().len())?;
+    } else if let Some(idx) = added_at {
+        writeln!(out, "Added chunk at index {}", idx)?;
+    }
+    Ok(())
+}
+
+fn print_dry_run(before: &Png, after: &Png) -> anyhow::Result<()> {
+    Ok(listing::write_dry_run(&mut io::stdout().lock(), before, after)?)
+}
+
+// Clap conflicts cannot depend on the value of `--mode`, so whether it takes a chunk type is checked here
+fn check_mode_chunk_type(mode: Option<EmbedMode>, has_chunk_type: bool) -> anyhow::Result<()> {
+    match mode {
+        #[cfg(feature = "compression")]
+        Some(EmbedMode::Lsb) if has_chunk_type => {
+            bail!("--mode lsb stores the message in the image samples, it cannot be combined with --chunk-type or --chunk-type-file")
+        }
+        Some(EmbedMode::Chunk) if !has_chunk_type => bail!("--mode chunk needs --chunk-type or --chunk-type-file"),
+        _ => Ok(()),
+    }
+}
+
+// Critical types such as IDAT are interpreted by decoders, so a message stored in one can break the image
+fn check_critical(chunk_type: &ChunkType, allow_critical: bool) -> anyhow::Result<()> {
+    if !chunk_type.is_critical() {
+        return Ok(());
+    }
+    if !allow_critical {
+        bail!("Chunk type {} is critical and encoding into it can corrupt the image, pass --allow-critical to proceed", chunk_type);
+    }
+    eprintln!("[WARN] - Encoding into critical chunk type: {}", chunk_type);
+    Ok(())
+}
+
+// Without an allow-list every chunk type may be decoded
+fn check_allowed(chunk_type: &ChunkType, allow_types: Option<&[ChunkType]>) -> anyhow::Result<()> {
+    match allow_types {
+        Some(allowed) if !allowed.contains(chunk_type) => {
+            let allowed: Vec<String> = allowed.iter().map(ChunkType::to_string).collect();
+            bail!("Chunk type {} is not in the allowed types: {}", chunk_type, allowed.join(", "))
+        }
+        _ => Ok(()),
+    }
+}
+
+// Returns the index of the new chunk, `None` when the message was stored after IEND
+// `payload` replaces the message bytes stored in a chunk of `chunk_type`, for compressed or tagged messages
+fn encode_message(png: &mut Png, message: &str, payload: Option<&[u8]>, text_chunk: Option<Chunk>, chunk_type: Option<&ChunkType>,
+                  after_iend: bool) -> anyhow::Result<Option<usize>> {
+    let chunk = match (text_chunk, chunk_type) {
+        _ if after_iend => {
+            let (other, _) = split_trailing_message(png.trailing_bytes());
+            let mut trailing = other.to_vec();
+            trailing.extend_from_slice(message.as_bytes());
+            trailing.extend_from_slice(&(message.len() as u32).to_be_bytes());
+            trailing.extend_from_slice(&TRAILING_MAGIC);
+            png.set_trailing_bytes(trailing);
+            return Ok(None);
+        }
+        (Some(text_chunk), _) => text_chunk,
+        (None, Some(chunk_type)) => Chunk::new(chunk_type.clone(), payload.unwrap_or(message.as_bytes()).to_vec()),
+        (None, None) => bail!("Either a chunk type or a text keyword is required"),
+    };
+    Ok(Some(png.append_chunk_indexed(chunk)))
+}
+
+// tEXt, or zTXt when compressed, unless `international` gives the language tag and translated keyword of an iTXt chunk
+fn text_chunk(keyword: &str, text: &str, compress: Option<PayloadCompression>, international: Option<(&str, &str)>) -> anyhow::Result<Chunk> {
+    if let Some((language, translated_keyword)) = international {
+        return Chunk::new_itxt(&InternationalText {
+            keyword: keyword.to_string(),
+            language: language.to_string(),
+            translated_keyword: translated_keyword.to_string(),
+            text: text.to_string(),
+            compressed: !matches!(compress, Some(PayloadCompression::None) | None),
+        });
+    }
+    match compress {
+        #[cfg(feature = "compression")]
+        Some(PayloadCompression::Deflate) => Chunk::new_ztxt(keyword, text),
+        Some(PayloadCompression::None) | None => Chunk::new_text(keyword, text),
+    }
+}
+
+// Text stored under `keyword`, looking in tEXt, then zTXt and then iTXt chunks
+fn text_by_keyword(png: &Png, keyword: &str, allow_types: Option<&[ChunkType]>) -> anyhow::Result<Option<String>> {
+    if let Some(text) = png.text_by_keyword(keyword) {
+        check_allowed(&ChunkType::from_str("tEXt")?, allow_types)?;
+        return Ok(Some(text));
+    }
+    #[cfg(feature = "compression")]
+    if let Some(text) = png.ztxt_by_keyword(keyword)? {
+        check_allowed(&ChunkType::from_str("zTXt")?, allow_types)?;
+        return Ok(Some(text));
+    }
+    let Some(entry) = png.itxt_entries()?.into_iter().find(|entry| entry.keyword == keyword) else {
+        return Ok(None);
+    };
+    check_allowed(&ChunkType::from_str("iTXt")?, allow_types)?;
+    Ok(Some(entry.text))
+}
+
+// A compressed message starts with a byte that never begins valid UTF-8, so plain text messages are never mistaken for one,
+// followed by the algorithm id leaving room for more algorithms
+#[cfg(feature = "compression")]
+const COMPRESSED_MARKER: u8 = 0xFF;
+#[cfg(feature = "compression")]
+const DEFLATE_ID: u8 = 1;
+
+fn pack_message(message: &[u8], compress: Option<PayloadCompression>) -> Vec<u8> {
+    match compress {
+        #[cfg(feature = "compression")]
+        Some(PayloadCompression::Deflate) => [COMPRESSED_MARKER, DEFLATE_ID].into_iter().chain(pngme::zlib::compress(message)).collect(),
+        Some(PayloadCompression::None) | None => message.to_vec(),
+    }
+}
+
+fn unpack_message(data: &[u8]) -> anyhow::Result<Cow<'_, [u8]>> {
+    #[cfg(feature = "compression")]
+    if let [COMPRESSED_MARKER, DEFLATE_ID, compressed @ ..] = data {
+        return Ok(Cow::Owned(pngme::zlib::decompress(compressed)?));
+    }
+    Ok(Cow::Borrowed(data))
+}
+
+// A message after IEND is followed by its big-endian u32 length and this magic, so it can be told apart from
+// whatever other tools appended there, and a new message replaces the old one instead of running into it
+const TRAILING_MAGIC: [u8; 8] = *b"pngmeMSG";
+
+// Splits the bytes after IEND into those of other tools and the framed message at their end, if any
+fn split_trailing_message(trailing: &[u8]) -> (&[u8], Option<&[u8]>) {
+    let Some(rest) = trailing.strip_suffix(&TRAILING_MAGIC) else {
+        return (trailing, None);
+    };
+    let Some((rest, length)) = rest.split_last_chunk::<4>() else {
+        return (trailing, None);
+    };
+    match rest.len().checked_sub(u32::from_be_bytes(*length) as usize) {
+        Some(start) => (&rest[..start], Some(&rest[start..])),
+        None => (trailing, None),
+    }
+}
+
+// A tagged message starts with another byte that never begins valid UTF-8 and the tag algorithm id,
+// followed by the HMAC-SHA256 of the rest of the message
+const TAGGED_MARKER: u8 = 0xFE;
+const HMAC_SHA256_ID: u8 = 1;
+const HMAC_LEN: usize = 32;
+
+fn tag_message(data: Vec<u8>, key: Option<&str>) -> anyhow::Result<Vec<u8>> {
+    let Some(key) = key else {
+        return Ok(data);
+    };
+    let tag = message_hmac(key, &data)?;
+    Ok([TAGGED_MARKER, HMAC_SHA256_ID].into_iter().chain(tag).chain(data).collect())
+}
+
+// Strips the tag of a tagged message, which must match when a key is given
+fn verify_message<'a>(data: &'a [u8], key: Option<&str>) -> anyhow::Result<&'a [u8]> {
+    let tagged = match data {
+        [TAGGED_MARKER, HMAC_SHA256_ID, rest @ ..] if rest.len() >= HMAC_LEN => Some(rest.split_at(HMAC_LEN)),
+        _ => None,
+    };
+    match (tagged, key) {
+        (Some((tag, message)), Some(key)) => {
+            // Compares every byte so the time taken does not reveal how much of the tag matched
+            let diff = tag.iter().zip(message_hmac(key, message)?).fold(0, |diff, (a, b)| diff | (a ^ b));
+            if diff != 0 {
+                bail!("Message failed HMAC verification, it was modified or the key is wrong");
+            }
+            Ok(message)
         }
-        Commands::Print { file_path } => {
-            let content = fs::read(&file_path)?;
-            let png = Png::try_from(content.as_slice())?;
-            png.chunks().iter().for_each(|chunk|
-                println!(
-                    "{}\n-----------", chunk
-                ));
+        (None, Some(_)) => bail!("Message has no HMAC tag to verify"),
+        (Some((_, message)), None) => {
+            eprintln!("[WARN] - Message has an HMAC tag that was not checked, pass --verify-key to verify it");
+            Ok(message)
         }
+        (None, None) => Ok(data),
+    }
+}
+
+#[cfg(feature = "sha256")]
+fn message_hmac(key: &str, data: &[u8]) -> anyhow::Result<[u8; HMAC_LEN]> {
+    Ok(pngme::sha256::hmac(key.as_bytes(), data))
+}
+
+#[cfg(not(feature = "sha256"))]
+fn message_hmac(_key: &str, _data: &[u8]) -> anyhow::Result<[u8; HMAC_LEN]> {
+    bail!("HMAC tags need pngme built with the sha256 feature")
+}
+
+// Strict decoding by default, so binary data isn't silently mangled
+fn payload_message(data: &[u8], lossy: bool, verify_key: Option<&str>) -> anyhow::Result<String> {
+    let data = unpack_message(verify_message(data, verify_key)?)?;
+    if lossy {
+        return Ok(String::from_utf8_lossy(&data).into_owned());
+    }
+    Ok(std::str::from_utf8(&data)?.to_string())
+}
+
+fn chunk_message(chunk: &Chunk, lossy: bool) -> anyhow::Result<String> {
+    payload_message(chunk.data(), lossy, None)
+}
+
+fn expect_message(png: &Png, chunk_type: &ChunkType, expected: &str) -> anyhow::Result<()> {
+    let chunk = png.chunk_by_type(&chunk_type.to_string())
+        .ok_or_else(|| PngError::ChunkTypeNotFound(chunk_type.to_string()))?;
+    let actual = chunk_message(chunk, false)?;
+    if actual != expected {
+        bail!("Message in {} does not match\n  expected: {:?}\n    actual: {:?}", chunk_type, expected, actual);
     }
     Ok(())
 }
+
+// Appends the data of the first `source_type` chunk of `source` to `dest` as a `dest_type` chunk
+fn copy_chunk(source: &Png, source_type: &ChunkType, dest: &mut Png, dest_type: ChunkType) -> anyhow::Result<()> {
+    let chunk = source.chunk_by_type(&source_type.to_string())
+        .ok_or_else(|| PngError::ChunkTypeNotFound(source_type.to_string()))?;
+    dest.append_chunk_indexed(Chunk::new(dest_type, chunk.data().to_vec()));
+    Ok(())
+}
+
+// Writes the length, type, data and CRC of a single chunk holding the bytes of `input_file`
+fn make_chunk(chunk_type: ChunkType, input_file: &str, output_file: &str) -> anyhow::Result<Chunk> {
+    let chunk = Chunk::new(chunk_type, stdio::read_input(input_file)?);
+    write_other(output_file, &chunk.as_bytes())?;
+    Ok(chunk)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_make_chunk_parses_back() {
+        let dir = std::env::temp_dir().join(format!("pngme-make-chunk-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let input = dir.join("input.bin");
+        let output = dir.join("chunk.bin");
+        fs::write(&input, [0u8, 159, 146, 150, 255]).unwrap();
+
+        let chunk = make_chunk(ChunkType::from_str("ruSt").unwrap(), input.to_str().unwrap(), output.to_str().unwrap()).unwrap();
+        let parsed = Chunk::try_from(fs::read(&output).unwrap().as_slice()).unwrap();
+        assert_eq!(parsed.chunk_type(), chunk.chunk_type());
+        assert_eq!(parsed.data(), &[0u8, 159, 146, 150, 255]);
+        assert_eq!(parsed.crc(), chunk.crc());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_read_png_no_signature() {
+        let path = std::env::temp_dir().join(format!("pngme-no-signature-{}", std::process::id()));
+        let png = Png::from_chunks(vec![Chunk::new(ChunkType::from_str("ruSt").unwrap(), b"headerless".to_vec())]);
+        fs::write(&path, &png.as_bytes()[8..]).unwrap();
+
+        let options = ReadOptions { lenient: false, recover: false, no_signature: false, input_format: InputFormat::Png };
+        assert!(read_png(path.to_str().unwrap(), options).is_err());
+        let png = read_png(path.to_str().unwrap(), ReadOptions { no_signature: true, ..options }).unwrap();
+        assert_eq!(png.chunks()[0].data(), b"headerless");
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_encode_with_chunk_type_file() {
+        let path = std::env::temp_dir().join(format!("pngme-chunk-type-{}", std::process::id()));
+        fs::write(&path, b"ruSt\n").unwrap();
+
+        let chunk_type = resolve_chunk_type(None, path.to_str()).unwrap();
+        let mut png = Png::from_chunks(Vec::new());
+        encode_message(&mut png, "from a file", None, None, chunk_type.as_ref(), false).unwrap();
+        assert_eq!(png.chunk_by_type("ruSt").unwrap().data(), b"from a file");
+
+        fs::write(&path, b"ruSt").unwrap();
+        assert_eq!(resolve_chunk_type(None, path.to_str()).unwrap(), chunk_type);
+
+        for content in [&b"ru"[..], b"ruStX", b"rust type", b"ruSt\n\n"] {
+            fs::write(&path, content).unwrap();
+            let err = resolve_chunk_type(None, path.to_str()).unwrap_err();
+            assert_eq!(err.downcast_ref::<FileContext>().unwrap().0, path.to_str().unwrap());
+            assert!(matches!(err.root_cause().downcast_ref(), Some(ChunkTypeError::WrongStringByteLength(_))), "{:?}", content);
+        }
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_check_critical() {
+        let idat = ChunkType::from_str("IDAT").unwrap();
+        let err = check_critical(&idat, false).unwrap_err();
+        assert!(err.to_string().starts_with("Chunk type IDAT is critical"));
+        assert!(check_critical(&idat, true).is_ok());
+        assert!(check_critical(&ChunkType::from_str("ruSt").unwrap(), false).is_ok());
+    }
+
+    #[test]
+    fn test_check_allowed() {
+        let allowed = [ChunkType::from_str("ruSt").unwrap()];
+        let err = check_allowed(&ChunkType::from_str("IDAT").unwrap(), Some(&allowed)).unwrap_err();
+        assert_eq!(err.to_string(), "Chunk type IDAT is not in the allowed types: ruSt");
+        assert!(check_allowed(&ChunkType::from_str("ruSt").unwrap(), Some(&allowed)).is_ok());
+        assert!(check_allowed(&ChunkType::from_str("IDAT").unwrap(), None).is_ok());
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_write_png_preserve_mtime() {
+        let dir = std::env::temp_dir().join(format!("pngme-mtime-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let (input, output) = (dir.join("input.png"), dir.join("output.png"));
+        let png = Png::from_chunks(Vec::new());
+        fs::write(&input, png.as_bytes()).unwrap();
+        let mtime = std::time::SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(1_000_000_000);
+        fs::File::options().write(true).open(&input).unwrap().set_modified(mtime).unwrap();
+
+        let options = WriteOptions { preserve_mtime: true, backup: false };
+        write_png(input.to_str().unwrap(), output.to_str().unwrap(), &png, options).unwrap();
+        assert_eq!(fs::metadata(&output).unwrap().modified().unwrap(), mtime);
+        write_png(input.to_str().unwrap(), output.to_str().unwrap(), &png, WriteOptions { preserve_mtime: false, ..options }).unwrap();
+        assert_ne!(fs::metadata(&output).unwrap().modified().unwrap(), mtime);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_binary_png_round_trips_byte_for_byte() {
+        // The signature's 0x89 byte alone makes every PNG invalid UTF-8
+        let content = include_bytes!("../fuzz/corpus/png_try_from/dice.png");
+        let dir = std::env::temp_dir().join(format!("pngme-binary-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let (input, output) = (dir.join("input.png"), dir.join("output.png"));
+        fs::write(&input, content).unwrap();
+
+        let options = ReadOptions { lenient: false, recover: false, no_signature: false, input_format: InputFormat::Auto };
+        let png = read_png(input.to_str().unwrap(), options).unwrap();
+        let options = WriteOptions { preserve_mtime: false, backup: false };
+        write_png(input.to_str().unwrap(), output.to_str().unwrap(), &png, options).unwrap();
+        assert_eq!(fs::read(&output).unwrap(), content);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_write_png_in_place_with_backup() {
+        let dir = std::env::temp_dir().join(format!("pngme-backup-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("image.png");
+        let original = Png::from_chunks(Vec::new());
+        fs::write(&path, original.as_bytes()).unwrap();
+
+        let mut png = original.clone();
+        encode_message(&mut png, "hi", None, None, Some(&ChunkType::from_str("ruSt").unwrap()), false).unwrap();
+        let path_str = path.to_str().unwrap();
+        write_png(path_str, path_str, &png, WriteOptions { preserve_mtime: false, backup: true }).unwrap();
+
+        assert_eq!(fs::read(&path).unwrap(), png.as_bytes());
+        assert_eq!(fs::read(dir.join("image.png.bak")).unwrap(), original.as_bytes());
+        // Only the output and its backup are left, the temporary file was renamed into place
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 2);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_encode_in_place_keeps_permissions() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let path = std::env::temp_dir().join(format!("pngme-private-{}.png", std::process::id()));
+        fs::write(&path, include_bytes!("../fuzz/corpus/png_try_from/dice.png")).unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o600)).unwrap();
+        run(Cli::try_parse_from(["pngme", "encode", "-f", path.to_str().unwrap(), "-c", "ruSt", "-m", "secret"]).unwrap()).unwrap();
+        assert_eq!(fs::metadata(&path).unwrap().permissions().mode() & 0o777, 0o600);
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_expect_message() {
+        let ru_st = ChunkType::from_str("ruSt").unwrap();
+        let png = Png::from_chunks(vec![Chunk::new(ru_st.clone(), b"watermark".to_vec())]);
+
+        assert!(expect_message(&png, &ru_st, "watermark").is_ok());
+        let err = expect_message(&png, &ru_st, "other").unwrap_err();
+        assert_eq!(err.to_string(), "Message in ruSt does not match\n  expected: \"other\"\n    actual: \"watermark\"");
+        assert!(expect_message(&png, &ChunkType::from_str("miSs").unwrap(), "watermark").is_err());
+    }
+
+    #[test]
+    fn test_check_mode_chunk_type() {
+        assert!(check_mode_chunk_type(None, true).is_ok());
+        assert!(check_mode_chunk_type(Some(EmbedMode::Chunk), true).is_ok());
+        let err = check_mode_chunk_type(Some(EmbedMode::Chunk), false).unwrap_err();
+        assert_eq!(err.to_string(), "--mode chunk needs --chunk-type or --chunk-type-file");
+        #[cfg(feature = "compression")]
+        {
+            assert!(check_mode_chunk_type(Some(EmbedMode::Lsb), false).is_ok());
+            assert!(check_mode_chunk_type(Some(EmbedMode::Lsb), true).unwrap_err().to_string().starts_with("--mode lsb stores"));
+        }
+    }
+
+    #[test]
+    fn test_keeping_crcs_does_not_recover() {
+        let keep_crcs = ReadOptions { lenient: true, recover: false, no_signature: false, input_format: InputFormat::Png };
+        let recover = ReadOptions { recover: true, ..keep_crcs };
+        let content = include_bytes!("../fuzz/corpus/png_try_from/dice.png");
+
+        let mut bad_crc = content.to_vec();
+        // The CRC of IHDR ends at 8 + 12 + 13
+        bad_crc[32] ^= 0xFF;
+        assert_eq!(parse_png(&bad_crc, keep_crcs).unwrap().as_bytes(), bad_crc);
+
+        let mut bad_signature = content.to_vec();
+        bad_signature[1] = b'Q';
+        assert!(matches!(parse_png(&bad_signature, keep_crcs).unwrap_err().downcast_ref(), Some(PngError::InvalidHeaderContent)));
+        assert!(parse_png(&bad_signature, recover).is_ok());
+
+        // Cut IEND, the CRC of RuSt and 2 of its 3 data bytes
+        let truncated = &content[..content.len() - 18];
+        assert!(parse_png(truncated, keep_crcs).unwrap_err().to_string().contains("is truncated"));
+        assert!(parse_png(truncated, recover).is_ok());
+    }
+
+    #[test]
+    fn test_message_after_iend_is_framed() {
+        let mut png = Png::try_from(&include_bytes!("../fuzz/corpus/png_try_from/dice.png")[..]).unwrap();
+        png.set_trailing_bytes(b"another tool's trailer".to_vec());
+        encode_message(&mut png, "first", None, None, None, true).unwrap();
+        encode_message(&mut png, "second", None, None, None, true).unwrap();
+
+        let png = Png::try_from(png.as_bytes().as_slice()).unwrap();
+        let (other, message) = split_trailing_message(png.trailing_bytes());
+        assert_eq!(other, b"another tool's trailer");
+        assert_eq!(message, Some(&b"second"[..]));
+
+        assert_eq!(split_trailing_message(b"no frame here"), (&b"no frame here"[..], None));
+        // A length reaching before the start of the trailing bytes is not a frame
+        let bogus = [&[0, 0, 0, 99][..], &TRAILING_MAGIC].concat();
+        assert_eq!(split_trailing_message(&bogus), (&bogus[..], None));
+    }
+
+    #[test]
+    fn test_missing_payload_file_is_named() {
+        let missing = std::env::temp_dir().join(format!("pngme-missing-{}", std::process::id()));
+        let missing = missing.to_str().unwrap();
+        let err = read_other(missing).unwrap_err();
+        let json = report::error_json(&err, Some("image.png"));
+        assert!(json.contains("\"kind\":\"Io\""), "{}", json);
+        assert!(json.contains(&format!("\"file\":\"{}\"", missing)), "{}", json);
+    }
+
+    #[test]
+    fn test_copy_chunk_retyped() {
+        let ru_st = ChunkType::from_str("ruSt").unwrap();
+        let source = Png::from_chunks(vec![Chunk::new(ru_st.clone(), b"payload".to_vec())]);
+        let mut dest = Png::try_from(&include_bytes!("../fuzz/corpus/png_try_from/dice.png")[..]).unwrap();
+
+        copy_chunk(&source, &ru_st, &mut dest, ChunkType::from_str("teSt").unwrap()).unwrap();
+        let dest = Png::try_from(dest.as_bytes().as_slice()).unwrap();
+        assert_eq!(dest.chunk_by_type("teSt").unwrap().data(), b"payload");
+        assert!(dest.chunk_by_type("ruSt").is_none());
+        let types: Vec<String> = dest.chunks().iter().rev().take(2).map(|chunk| chunk.chunk_type().to_string()).collect();
+        assert_eq!(types, ["IEND", "teSt"]);
+        assert!(dest.validate().is_empty());
+
+        let missing = ChunkType::from_str("miSs").unwrap();
+        assert!(copy_chunk(&source, &missing, &mut Png::from_chunks(Vec::new()), ru_st).is_err());
+    }
+
+    #[test]
+    fn test_decode_input_auto() {
+        let png = Png::from_chunks(vec![
+            Chunk::new(ChunkType::from_str("IHDR").unwrap(), vec![0; 13]),
+            Chunk::new(ChunkType::from_str("ruSt").unwrap(), b"any format".to_vec()),
+        ]);
+        let raw = png.as_bytes();
+        let inputs = [raw.clone(), base64::encode(&raw).into_bytes(), format!("{}\n", hex::encode(&raw)).into_bytes()];
+        for input in inputs {
+            let decoded = decode_input(input, InputFormat::Auto).unwrap();
+            assert_eq!(Png::try_from(decoded.as_slice()).unwrap().chunks(), png.chunks());
+        }
+
+        assert!(decode_input(raw.clone(), InputFormat::Hex).is_err());
+        assert_eq!(decode_input(raw.clone(), InputFormat::Png).unwrap(), raw);
+    }
+
+    #[test]
+    fn test_write_png_to_directory() {
+        let dir = std::env::temp_dir();
+        let png = Png::from_chunks(Vec::new());
+        let options = WriteOptions { preserve_mtime: false, backup: false };
+        let err = write_png("unused", dir.to_str().unwrap(), &png, options).unwrap_err();
+        assert_eq!(err.root_cause().to_string(), format!("output path is a directory: {}", dir.display()));
+    }
+
+    #[test]
+    fn test_finish_encode_reports_only_after_writing() {
+        let before = Png::try_from(&include_bytes!("../fuzz/corpus/png_try_from/dice.png")[..]).unwrap();
+        let mut png = before.clone();
+        let idx = encode_message(&mut png, "hi", None, None, Some(&ChunkType::from_str("ruSt").unwrap()), false).unwrap();
+        let finish = |before: Option<&Png>, written: anyhow::Result<bool>| {
+            let (mut out, mut called) = (Vec::new(), false);
+            let result = finish_encode(&mut out, before, &png, idx, false, || {
+                called = true;
+                written
+            });
+            (result.is_ok(), called, String::from_utf8(out).unwrap())
+        };
+
+        let (ok, called, out) = finish(Some(&before), Ok(true));
+        assert!(ok && !called);
+        assert!(out.starts_with("+ 6 ruSt (2 bytes)\nDry run"), "{}", out);
+        assert!(!out.contains("Added chunk"));
+
+        assert_eq!(finish(None, Ok(true)), (true, true, "Added chunk at index 6\n".to_string()));
+        assert_eq!(finish(None, Ok(false)), (true, true, String::new()));
+        assert_eq!(finish(None, Err(anyhow::anyhow!("disk full"))), (false, true, String::new()));
+    }
+
+    #[test]
+    fn test_encode_repeat() {
+        let dir = std::env::temp_dir();
+        let input = dir.join(format!("pngme-repeat-in-{}.png", std::process::id()));
+        let output = dir.join(format!("pngme-repeat-out-{}.png", std::process::id()));
+        fs::write(&input, include_bytes!("../fuzz/corpus/png_try_from/dice.png")).unwrap();
+        let args = |repeat: &str| ["pngme", "encode", "-f", input.to_str().unwrap(), "-c", "ruSt", "-m", "again",
+                                   "-o", output.to_str().unwrap(), "--repeat", repeat].map(str::to_string);
+
+        run(Cli::try_parse_from(args("100")).unwrap()).unwrap();
+        let png = Png::try_from(fs::read(&output).unwrap().as_slice()).unwrap();
+        assert_eq!(png.chunks().iter().filter(|chunk| *chunk.chunk_type() == *b"ruSt" && chunk.data() == b"again").count(), 100);
+        assert!(png.validate().is_empty());
+
+        assert!(Cli::try_parse_from(args("0")).is_err());
+        fs::remove_file(&input).unwrap();
+        fs::remove_file(&output).unwrap();
+    }
+
+    #[test]
+    fn test_strip_in_place() {
+        let dir = std::env::temp_dir().join(format!("pngme-strip-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("dice.png");
+        fs::write(&path, include_bytes!("../fuzz/corpus/png_try_from/dice.png")).unwrap();
+
+        let args = ["pngme", "--backup", "strip", "-f", path.to_str().unwrap(), "-k", "sRGB"];
+        run(Cli::try_parse_from(args).unwrap()).unwrap();
+        let png = Png::try_from(fs::read(&path).unwrap().as_slice()).unwrap();
+        let types: Vec<String> = png.chunks().iter().map(|chunk| chunk.chunk_type().to_string()).collect();
+        assert_eq!(types, ["IHDR", "sRGB", "IDAT", "RuSt", "IEND"]);
+        assert_eq!(fs::read(dir.join("dice.png.bak")).unwrap(), include_bytes!("../fuzz/corpus/png_try_from/dice.png"));
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 2);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_file_digest() {
+        let content = include_bytes!("../fuzz/corpus/png_try_from/dice.png");
+        let crc32 = file_digest(content, ChecksumAlgorithm::Crc32);
+        assert_eq!(crc32.len(), 8);
+        assert_eq!(crc32, file_digest(content, ChecksumAlgorithm::Crc32));
+        assert_eq!(crc32, "04101d38");
+
+        #[cfg(feature = "sha256")]
+        {
+            let sha256 = file_digest(content, ChecksumAlgorithm::Sha256);
+            assert_eq!(sha256.len(), 64);
+            assert_eq!(sha256, file_digest(content, ChecksumAlgorithm::Sha256));
+            assert_eq!(sha256, "15a3dbdc455d238ef2d420f0581324ec1b320502fd0fe59eb8309b9cac412e88");
+        }
+
+        #[cfg(feature = "blake3")]
+        {
+            let blake3 = file_digest(content, ChecksumAlgorithm::Blake3);
+            assert_eq!(blake3.len(), 64);
+            assert_eq!(blake3, "a4fee3560eaa9200e1b2b9a09fa3325c1b845174c011bc4d8b7ec6837b3e9f84");
+        }
+    }
+
+    #[test]
+    fn test_chunk_message_lossy() {
+        let chunk = Chunk::new(ChunkType::from_str("ruSt").unwrap(), b"mostly \xFF text".to_vec());
+        assert!(chunk_message(&chunk, false).is_err());
+        assert_eq!(chunk_message(&chunk, true).unwrap(), "mostly \u{FFFD} text");
+    }
+
+    #[test]
+    #[cfg(feature = "sha256")]
+    fn test_tagged_message_verification() {
+        let tagged = tag_message(b"signed, sealed".to_vec(), Some("key")).unwrap();
+        assert_eq!(payload_message(&tagged, false, Some("key")).unwrap(), "signed, sealed");
+        assert_eq!(payload_message(&tagged, false, None).unwrap(), "signed, sealed");
+        assert!(payload_message(&tagged, false, Some("other key")).is_err());
+        assert!(payload_message(b"signed, sealed", false, Some("key")).is_err());
+
+        let mut tampered = tagged.clone();
+        *tampered.last_mut().unwrap() ^= 1;
+        let err = payload_message(&tampered, false, Some("key")).unwrap_err();
+        assert!(err.to_string().contains("failed HMAC verification"));
+    }
+
+    #[test]
+    #[cfg(feature = "compression")]
+    fn test_compressed_message_round_trips() {
+        let message = "compress me ".repeat(50);
+        let packed = pack_message(message.as_bytes(), Some(PayloadCompression::Deflate));
+        assert!(packed.len() < message.len());
+        assert_eq!(payload_message(&packed, false, None).unwrap(), message);
+
+        assert_eq!(pack_message(b"plain", Some(PayloadCompression::None)), b"plain");
+        assert_eq!(payload_message(b"plain", false, None).unwrap(), "plain");
+        // Other binary data starting with the marker byte is left alone
+        assert_eq!(payload_message(b"\xFF\x07 raw", true, None).unwrap(), "\u{FFFD}\u{7} raw");
+    }
+}
@@ -0,0 +1,88 @@
+use std::ffi::{c_int, c_long, c_void};
+use std::fs::File;
+use std::io;
+use std::ops::Deref;
+use std::os::unix::io::AsRawFd;
+use std::path::Path;
+use std::ptr;
+
+const PROT_READ: c_int = 1;
+const MAP_PRIVATE: c_int = 2;
+
+// The `off_t` of the plain `mmap` symbol is a C long on the unix targets Rust supports
+extern "C" {
+    fn mmap(addr: *mut c_void, len: usize, prot: c_int, flags: c_int, fd: c_int, offset: c_long) -> *mut c_void;
+    fn munmap(addr: *mut c_void, len: usize) -> c_int;
+}
+
+/// A read-only memory map of a whole file, so huge PNGs can be parsed with `Png::try_from(&*map)` without copying them onto the heap first
+pub struct Mmap {
+    ptr: *mut c_void,
+    len: usize,
+}
+
+impl Mmap {
+    /// Maps the file at `path`
+    ///
+    /// # Safety
+    ///
+    /// The file must not be truncated or modified while the map is alive, the mapped bytes change with it
+    /// and reading past a truncated end raises SIGBUS.
+    pub unsafe fn open<P: AsRef<Path>>(path: P) -> io::Result<Mmap> {
+        let file = File::open(path)?;
+        let len = usize::try_from(file.metadata()?.len())
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "file is too large to map"))?;
+        // mmap rejects empty mappings, an empty file is just an empty slice
+        if len == 0 {
+            return Ok(Mmap { ptr: ptr::null_mut(), len });
+        }
+        // SAFETY: a private read-only mapping of an open file, the fd may be closed once mapped
+        let ptr = unsafe { mmap(ptr::null_mut(), len, PROT_READ, MAP_PRIVATE, file.as_raw_fd(), 0) };
+        if ptr as isize == -1 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(Mmap { ptr, len })
+    }
+}
+
+impl Deref for Mmap {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        if self.len == 0 {
+            return &[];
+        }
+        // SAFETY: `ptr` points to `len` mapped readable bytes until `munmap` in `drop`
+        unsafe { std::slice::from_raw_parts(self.ptr as *const u8, self.len) }
+    }
+}
+
+impl Drop for Mmap {
+    fn drop(&mut self) {
+        if self.len != 0 {
+            // SAFETY: unmaps exactly the region returned by `mmap`
+            unsafe { munmap(self.ptr, self.len) };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Png;
+
+    #[test]
+    fn test_parse_mapped_file() {
+        let path = std::env::temp_dir().join(format!("pngme-mmap-{}.png", std::process::id()));
+        std::fs::write(&path, include_bytes!("../fuzz/corpus/png_try_from/dice.png")).unwrap();
+        // SAFETY: the test owns the file and only rewrites it after the map is dropped
+        let map = unsafe { Mmap::open(&path) }.unwrap();
+        let png = Png::try_from(&*map).unwrap();
+        assert_eq!(png.as_bytes(), &*map);
+        drop(map);
+
+        std::fs::write(&path, b"").unwrap();
+        assert!(unsafe { Mmap::open(&path) }.unwrap().is_empty());
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -1,48 +1,378 @@
 use std::fmt::{Display, Formatter};
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
 use std::str::FromStr;
 
 use anyhow::bail;
 use thiserror::Error;
 
-use crate::chunk::{Chunk, ChunkError};
+use crate::base64;
+use crate::chunk::{Chunk, InternationalText};
 use crate::chunk_type::ChunkType;
+use crate::ihdr::Ihdr;
+use crate::json::Value;
 
 #[derive(Error, Debug)]
 pub enum PngError {
     #[error("Chunk of type {0} could not be found")]
     ChunkTypeNotFound(String),
-    #[error("Expected 8 bytes for header length, less were provided")]
-    InvalidHeaderLength,
+    #[error("File is too small to be a Png, expected at least 8 bytes for the signature but got {0}")]
+    FileTooSmall(usize),
     #[error("Header content does not match standard header value for Png files")]
     InvalidHeaderContent,
-    #[error("Chunk length in bytes is invalid")]
-    InvalidChunkLength,
+    #[error("Chunk length bytes at offset {0} are invalid")]
+    InvalidChunkLength(usize),
+    #[error("Invalid chunk at byte offset {offset}")]
+    InvalidChunk {
+        offset: usize,
+        #[source]
+        source: anyhow::Error,
+    },
+    #[error("Chunk index {0} is out of bounds, the Png has {1} chunks")]
+    ChunkIndexOutOfBounds(usize, usize),
+    #[error("hIST length {0} must be 2 bytes per palette entry, {1} expected")]
+    InvalidHistogramLength(u32, usize),
+    #[error("Payload piece {0} is missing or has an invalid frame")]
+    InvalidPayloadPiece(usize),
+    #[error("Payload of {0} bytes needs more chunks than its frames can number")]
+    PayloadTooLarge(usize),
+    #[error("Piece size {0} must be between 1 and {max} bytes", max = Png::MAX_PIECE_SIZE)]
+    InvalidPieceSize(usize),
+    #[error("Revealed payload has CRC {0} but {1} was stored, a piece is missing or out of order")]
+    PayloadCrcMismatch(u32, u32),
 }
 
+impl PngError {
+    /// Stable, machine-readable name of the error variant
+    pub fn kind(&self) -> &'static str {
+        match self {
+            PngError::ChunkTypeNotFound(_) => "ChunkNotFound",
+            PngError::FileTooSmall(_) => "FileTooSmall",
+            PngError::InvalidHeaderContent => "InvalidHeaderContent",
+            PngError::InvalidChunkLength(_) => "InvalidChunkLength",
+            PngError::InvalidChunk { .. } => "InvalidChunk",
+            PngError::ChunkIndexOutOfBounds(..) => "ChunkIndexOutOfBounds",
+            PngError::InvalidHistogramLength(..) => "InvalidHistogramLength",
+            PngError::InvalidPayloadPiece(_) => "InvalidPayloadPiece",
+            PngError::PayloadTooLarge(_) => "PayloadTooLarge",
+            PngError::PayloadCrcMismatch(..) => "PayloadCrcMismatch",
+            PngError::InvalidPieceSize(_) => "InvalidPieceSize",
+        }
+    }
+
+    /// Byte offset into the file the error refers to, if any
+    pub fn offset(&self) -> Option<usize> {
+        match self {
+            PngError::InvalidChunkLength(offset) | PngError::InvalidChunk { offset, .. } => Some(*offset),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Severity {
+    /// The file breaks a rule of the spec and decoders may reject it
+    Error,
+    /// The file is valid but unusual enough to point out
+    Warning,
+}
+
+impl Display for Severity {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Severity::Error => write!(f, "error"),
+            Severity::Warning => write!(f, "warning"),
+        }
+    }
+}
+
+/// A problem found by `Png::violations`, at the byte offset it starts at when it has one
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Violation {
+    pub offset: Option<usize>,
+    pub severity: Severity,
+    pub message: String,
+}
+
+/// Owned description of a chunk for table and JSON output, without the chunk data
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct ChunkSummary {
+    pub chunk_type: String,
+    pub length: u32,
+    pub crc: u32,
+    pub critical: bool,
+    pub public: bool,
+    pub reserved_bit_valid: bool,
+    pub safe_to_copy: bool,
+}
+
+/// One difference between the chunks of two Pngs, `index` points into the Png holding the chunk
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum ChunkChange {
+    Added { index: usize, chunk_type: ChunkType, length: u32 },
+    Removed { index: usize, chunk_type: ChunkType, length: u32 },
+    /// Same type in the same place with different content, `index` is the position in the original Png
+    Modified { index: usize, chunk_type: ChunkType, before: u32, after: u32 },
+}
+
+/// Chunk changes turning one Png into another, in file order
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub struct PngDiff {
+    pub changes: Vec<ChunkChange>,
+}
+
+impl PngDiff {
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
 pub struct Png {
     chunks: Vec<Chunk>,
+    valid_signature: bool,
+    // Anything found after the IEND chunk, written back unchanged
+    trailing: Vec<u8>,
 }
 
 impl Png {
-    const STANDARD_HEADER: [u8; 8] = [137, 80, 78, 71, 13, 10, 26, 10];
+    /// The 8 byte signature every Png file starts with
+    pub const STANDARD_HEADER: [u8; 8] = [137, 80, 78, 71, 13, 10, 26, 10];
+    /// Private ancillary chunk type holding the JSON metadata object
+    pub const METADATA_CHUNK_TYPE: &'static str = "meTa";
+    /// Standard chunk types holding textual metadata
+    pub const TEXT_CHUNK_TYPES: [&'static str; 3] = ["tEXt", "zTXt", "iTXt"];
+    /// Compressed payload bytes stored per chunk by `hide_payload`
+    pub const PAYLOAD_PIECE_SIZE: usize = 8192;
+    /// Default payload bytes stored per chunk by `append_split`
+    pub const SPLIT_PIECE_SIZE: usize = 1 << 20;
+    /// Largest piece `append_split` accepts, keeping the framed chunk within the 2^31 - 1 length limit
+    pub const MAX_PIECE_SIZE: usize = i32::MAX as usize - SPLIT_FRAME_LEN;
+
+    /// Reads only the first 8 bytes of `r` and checks them against the signature, a shorter input is not a Png
+    pub fn peek_signature<R: Read>(r: &mut R) -> io::Result<bool> {
+        let mut signature = [0u8; 8];
+        match r.read_exact(&mut signature) {
+            Ok(()) => Ok(signature == Png::STANDARD_HEADER),
+            Err(err) if err.kind() == io::ErrorKind::UnexpectedEof => Ok(false),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Parses a Png like `try_from` while reading one chunk at a time, so memory is bounded by the largest chunk
+    /// rather than the file. Only the bytes after IEND are buffered, to tell further chunks from trailing data
+    pub fn from_reader<R: Read>(r: &mut R) -> anyhow::Result<Png> {
+        let mut signature = Vec::with_capacity(8);
+        r.take(8).read_to_end(&mut signature)?;
+        if signature.len() < 8 {
+            bail!(PngError::FileTooSmall(signature.len()));
+        }
+        if signature != Png::STANDARD_HEADER {
+            bail!(PngError::InvalidHeaderContent);
+        }
 
-    fn from_chunks(chunks: Vec<Chunk>) -> Png {
+        let mut chunks = Vec::new();
+        let mut offset = signature.len();
+        loop {
+            let mut bytes = Vec::with_capacity(12);
+            r.take(4).read_to_end(&mut bytes)?;
+            match bytes.len() {
+                0 => break,
+                4 => {}
+                _ => bail!(PngError::InvalidChunkLength(offset)),
+            }
+            let length = u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+            // Bounds the read, an overflowing length is then rejected by the chunk parser
+            r.take(length.min(1 << 31) as u64 + 8).read_to_end(&mut bytes)?;
+            let chunk = Chunk::try_from(bytes.as_slice()).map_err(|source| PngError::InvalidChunk { offset, source })?;
+            offset += bytes.len();
+            let is_end = *chunk.chunk_type() == *b"IEND";
+            chunks.push(chunk);
+            if is_end {
+                let mut rest = Vec::new();
+                r.read_to_end(&mut rest)?;
+                let mut png = Png::parse_chunks(&rest, 0, false, true)?;
+                chunks.append(&mut png.chunks);
+                png.chunks = chunks;
+                return Ok(png);
+            }
+        }
+        Ok(Png::from_chunks(chunks))
+    }
+
+    pub fn from_chunks(chunks: Vec<Chunk>) -> Png {
         Png {
-            chunks
+            chunks,
+            valid_signature: true,
+            trailing: Vec::new(),
+        }
+    }
+
+    /// Parses a Png while tolerating a damaged signature and CRC mismatches, so they can be reported by `validate`
+    pub fn from_bytes_lenient(value: &[u8]) -> anyhow::Result<Png> {
+        Png::parse(value, true)
+    }
+
+    /// Parses a headerless stream of chunks starting at byte 0, as dumped by tools that strip the signature
+    pub fn from_chunk_stream(value: &[u8], lenient: bool) -> anyhow::Result<Png> {
+        Png::parse_chunks(value, 0, lenient, false)
+    }
+
+    /// Runs every structural check and returns a description of each error found, empty means valid
+    pub fn validate(&self) -> Vec<String> {
+        self.violations().into_iter()
+            .filter(|violation| violation.severity == Severity::Error)
+            .map(|violation| violation.message)
+            .collect()
+    }
+
+    /// Checks the signature, chunk ordering, CRCs, chunk lengths and palette against the spec, in file order per check
+    pub fn violations(&self) -> Vec<Violation> {
+        let mut violations = Vec::new();
+        let mut error = |offset: Option<usize>, message: String| violations.push(Violation { offset, severity: Severity::Error, message });
+        let types: Vec<String> = self.chunks.iter().map(|chunk| chunk.chunk_type().to_string()).collect();
+        let offsets: Vec<usize> = self.chunks.iter()
+            .scan(Png::STANDARD_HEADER.len(), |offset, chunk| Some(std::mem::replace(offset, *offset + chunk.total_size())))
+            .collect();
+        let end_of_chunks = offsets.last().zip(self.chunks.last())
+            .map_or(Png::STANDARD_HEADER.len(), |(offset, chunk)| offset + chunk.total_size());
+        let positions = |name: &str| types.iter().enumerate().filter(|(_, t)| *t == name).map(|(idx, _)| idx).collect::<Vec<_>>();
+        let first_idat = types.iter().position(|t| t == "IDAT");
+
+        if !self.valid_signature {
+            error(Some(0), "Signature does not match the standard Png signature".to_string());
+        }
+
+        // Ordering
+        if types.first().map(String::as_str) != Some("IHDR") {
+            error(Some(Png::STANDARD_HEADER.len()), "First chunk must be IHDR".to_string());
+        }
+        if types.last().map(String::as_str) != Some("IEND") {
+            error(offsets.last().copied(), "Last chunk must be IEND".to_string());
+        }
+        for name in ["IHDR", "IEND", "PLTE"] {
+            let found = positions(name);
+            if found.len() > 1 {
+                error(Some(offsets[found[1]]), format!("Found {} {} chunks, at most one is allowed", found.len(), name));
+            }
+        }
+        match first_idat {
+            None => error(None, "No IDAT chunk found".to_string()),
+            Some(first) => {
+                let last = types.iter().rposition(|t| t == "IDAT").unwrap();
+                if let Some(gap) = types[first..=last].iter().position(|t| t != "IDAT") {
+                    error(Some(offsets[first + gap]), "IDAT chunks must be consecutive".to_string());
+                }
+                if let Some(plte) = types.iter().rposition(|t| t == "PLTE").filter(|plte| *plte > first) {
+                    error(Some(offsets[plte]), "PLTE chunk must come before the first IDAT chunk".to_string());
+                }
+            }
+        }
+
+        // Per-chunk CRC and length
+        for (idx, chunk) in self.chunks.iter().enumerate() {
+            let offset = offsets[idx];
+            if !chunk.has_valid_crc() {
+                error(Some(offset + 8 + chunk.length() as usize), format!("Chunk {} ({}) has CRC {} but {} was expected",
+                                                                          idx, chunk.chunk_type(), chunk.stored_crc(), chunk.crc()));
+            }
+            if chunk.is_truncated() {
+                error(Some(offset), format!("Chunk {} ({}) is truncated after {} data bytes", idx, chunk.chunk_type(), chunk.length()));
+            }
+            if chunk.length() > i32::MAX as u32 {
+                error(Some(offset), format!("Chunk {} ({}) length {} exceeds 2^31 - 1", idx, chunk.chunk_type(), chunk.length()));
+            }
+            let expected_length = match types[idx].as_str() {
+                "IHDR" => Some(13),
+                "IEND" => Some(0),
+                _ => None,
+            };
+            if expected_length.is_some_and(|expected| expected != chunk.length()) {
+                error(Some(offset), format!("Chunk {} ({}) has length {}, expected {}",
+                                            idx, chunk.chunk_type(), chunk.length(), expected_length.unwrap()));
+            }
+        }
+
+        // Header fields, a wrong length is already reported above
+        if let Some(idx) = types.iter().position(|t| t == "IHDR").filter(|idx| self.chunks[*idx].length() == 13) {
+            if let Err(err) = Ihdr::try_from(self.chunks[idx].data()) {
+                error(Some(offsets[idx] + 8), format!("IHDR is invalid: {}", err));
+            }
+        }
+
+        // Palette
+        let color_type = self.chunk_by_type("IHDR")
+            .filter(|ihdr| ihdr.length() == 13)
+            .map(|ihdr| ihdr.data()[9]);
+        match types.iter().position(|t| t == "PLTE") {
+            Some(idx) => {
+                let plte = &self.chunks[idx];
+                if !plte.length().is_multiple_of(3) || !(1..=256).contains(&(plte.length() / 3)) {
+                    error(Some(offsets[idx]), format!("PLTE length {} must be a multiple of 3 holding 1 to 256 entries", plte.length()));
+                }
+                if matches!(color_type, Some(0) | Some(4)) {
+                    error(Some(offsets[idx]), format!("PLTE chunk is not allowed for color type {}", color_type.unwrap()));
+                }
+            }
+            None if color_type == Some(3) => error(None, "Color type 3 requires a PLTE chunk".to_string()),
+            None => {}
+        }
+        if let Err(err) = self.histogram() {
+            error(types.iter().position(|t| t == "hIST").map(|idx| offsets[idx]), err.to_string());
+        }
+
+        // Legal but suspicious, readers accept these
+        for (idx, chunk) in self.chunks.iter().enumerate() {
+            if !chunk.chunk_type().is_reserved_bit_valid() {
+                violations.push(Violation {
+                    offset: Some(offsets[idx] + 4),
+                    severity: Severity::Warning,
+                    message: format!("Chunk {} ({}) has the reserved bit set", idx, chunk.chunk_type()),
+                });
+            }
+        }
+        if !self.trailing.is_empty() {
+            violations.push(Violation {
+                offset: Some(end_of_chunks),
+                severity: Severity::Warning,
+                message: format!("{} bytes of trailing data after the last chunk", self.trailing.len()),
+            });
         }
+
+        violations
+    }
+
+    /// Palette entry frequencies from the hIST chunk, one per PLTE entry, `None` without a hIST chunk
+    pub fn histogram(&self) -> anyhow::Result<Option<Vec<u16>>> {
+        let Some(hist) = self.chunk_by_type("hIST") else {
+            return Ok(None);
+        };
+        let plte = self.chunk_by_type("PLTE").ok_or_else(|| PngError::ChunkTypeNotFound("PLTE".to_string()))?;
+        let expected = plte.length() as usize / 3 * 2;
+        if hist.length() as usize != expected {
+            bail!(PngError::InvalidHistogramLength(hist.length(), expected));
+        }
+        Ok(Some(hist.data().chunks(2).map(|pair| u16::from_be_bytes([pair[0], pair[1]])).collect()))
     }
 
     pub fn append_chunk(&mut self, chunk: Chunk) {
         self.chunks.push(chunk)
     }
 
+    /// Appends `chunk` before a final IEND, or at the end when there is none, and returns where it landed
+    pub fn append_chunk_indexed(&mut self, chunk: Chunk) -> usize {
+        let idx = match self.chunks.last() {
+            Some(last) if *last.chunk_type() == *b"IEND" => self.chunks.len() - 1,
+            _ => self.chunks.len(),
+        };
+        self.chunks.insert(idx, chunk);
+        idx
+    }
+
     pub fn remove_chunk(&mut self, chunk_type: &str) -> anyhow::Result<Chunk> {
         let chunk_type = ChunkType::from_str(chunk_type)?;
-        let idx = self.chunks
-            .iter()
-            .enumerate()
-            .find(|(_, chunk)| *chunk.chunk_type() == chunk_type)
-            .map(|x| x.0);
+        let idx = self.position_of(|chunk| *chunk.chunk_type() == chunk_type);
 
         if let Some(idx_to_del) = idx {
             return Ok(self.chunks.remove(idx_to_del));
@@ -51,60 +381,647 @@ impl Png {
         bail!(PngError::ChunkTypeNotFound(chunk_type.to_string()))
     }
 
-    fn header(&self) -> &[u8; 8] {
+    /// Removes the chunk at `index` in O(1) by moving the last chunk into its place, so the order is disturbed
+    /// and the chunks need to be put back in order before the file is serialized
+    pub fn swap_remove_chunk(&mut self, index: usize) -> anyhow::Result<Chunk> {
+        if index >= self.chunks.len() {
+            bail!(PngError::ChunkIndexOutOfBounds(index, self.chunks.len()));
+        }
+        Ok(self.chunks.swap_remove(index))
+    }
+
+    /// Rebuilds the first chunk of `chunk_type` with `data` and a fresh CRC, keeping its position
+    pub fn replace_data_of_type(&mut self, chunk_type: &str, data: Vec<u8>) -> anyhow::Result<()> {
+        let chunk_type = ChunkType::from_str(chunk_type)?;
+        let idx = self.position_of(|chunk| *chunk.chunk_type() == chunk_type)
+            .ok_or_else(|| PngError::ChunkTypeNotFound(chunk_type.to_string()))?;
+        self.chunks[idx] = Chunk::new(chunk_type, data);
+        Ok(())
+    }
+
+    pub fn swap_chunks(&mut self, i: usize, j: usize) -> anyhow::Result<()> {
+        let len = self.chunks.len();
+        if let Some(idx) = [i, j].into_iter().find(|idx| *idx >= len) {
+            bail!(PngError::ChunkIndexOutOfBounds(idx, len));
+        }
+        self.chunks.swap(i, j);
+        Ok(())
+    }
+
+    /// Moves every chunk out in order, leaving the Png without chunks
+    pub fn drain_chunks(&mut self) -> impl Iterator<Item = Chunk> + '_ {
+        self.chunks.drain(..)
+    }
+
+    /// Keeps only the chunks for which `keep` returns true, in their original order
+    pub fn retain_chunks<F: FnMut(&Chunk) -> bool>(&mut self, keep: F) {
+        self.chunks.retain(keep);
+    }
+
+    /// Copy holding only the critical chunks and the ancillary chunks whose type is in `keep`
+    pub fn stripped(&self, keep: &[ChunkType]) -> Png {
+        let mut stripped = self.clone();
+        stripped.retain_chunks(|chunk| chunk.chunk_type().is_critical() || keep.contains(chunk.chunk_type()));
+        stripped
+    }
+
+    /// Writes the `stripped` copy to `path`
+    pub fn write_stripped(&self, path: &Path, keep: &[ChunkType]) -> anyhow::Result<()> {
+        self.stripped(keep).write_atomically(path)
+    }
+
+    /// Writes the PNG to a temporary file next to `path` and renames it into place,
+    /// so a failed write leaves any existing file untouched and a replaced file keeps its permissions
+    pub fn write_atomically(&self, path: &Path) -> anyhow::Result<()> {
+        let name = path.file_name().ok_or_else(|| anyhow::anyhow!("output path has no file name: {}", path.display()))?;
+        let tmp = path.with_file_name(format!(".{}.{}.tmp", name.to_string_lossy(), std::process::id()));
+        let permissions = fs::metadata(path).ok().filter(|metadata| metadata.is_file()).map(|metadata| metadata.permissions());
+        let written = self.write_synced(&tmp).and_then(|()| {
+            if let Some(permissions) = permissions {
+                fs::set_permissions(&tmp, permissions)?;
+            }
+            Ok(fs::rename(&tmp, path)?)
+        });
+        if written.is_err() {
+            let _ = fs::remove_file(&tmp);
+        }
+        written
+    }
+
+    fn write_synced(&self, path: &Path) -> anyhow::Result<()> {
+        let file = fs::File::create(path)?;
+        let mut writer = io::BufWriter::new(&file);
+        self.write_to(&mut writer)?;
+        writer.flush()?;
+        drop(writer);
+        file.sync_all()?;
+        Ok(())
+    }
+
+    /// Removes every tEXt, zTXt and iTXt chunk and returns how many were removed
+    pub fn strip_text(&mut self) -> usize {
+        let before = self.chunks.len();
+        self.retain_chunks(|chunk| !Png::TEXT_CHUNK_TYPES.contains(&chunk.chunk_type().to_string().as_str()));
+        before - self.chunks.len()
+    }
+
+    /// Drops the last ancillary chunks until at most `max` remain, critical chunks such as IHDR and IEND are always kept
+    pub fn truncate_chunks(&mut self, max: usize) {
+        while self.chunks.len() > max {
+            match self.chunks.iter().rposition(|chunk| !chunk.chunk_type().is_critical()) {
+                Some(idx) => self.chunks.remove(idx),
+                None => break,
+            };
+        }
+    }
+
+    /// Reorders the chunks as the spec lays them out, IHDR first, IEND last and the IDAT chunks together,
+    /// chunks that may appear anywhere stay on their side of the IDAT chunks
+    pub fn canonicalize(&mut self) {
+        let first_idat = self.first_idat_index().unwrap_or(self.chunks.len());
+        let mut ranked: Vec<(u8, Chunk)> = self.chunks
+            .drain(..)
+            .enumerate()
+            .map(|(idx, chunk)| (canonical_rank(chunk.chunk_type(), idx < first_idat), chunk))
+            .collect();
+        ranked.sort_by_key(|(rank, _)| *rank);
+        self.chunks = ranked.into_iter().map(|(_, chunk)| chunk).collect();
+    }
+
+    /// Merges every IDAT chunk into one at the position of the first, the image data itself is unchanged
+    pub fn coalesce_idat(&mut self) {
+        let Some(first) = self.first_idat_index() else {
+            return;
+        };
+        let idat = Chunk::new(self.chunks[first].chunk_type().clone(), self.idat_stream());
+        self.chunks.retain(|chunk| *chunk.chunk_type() != *b"IDAT");
+        self.chunks.insert(first, idat);
+    }
+
+    /// Minifies the file for serving: only critical chunks in canonical order, one IDAT chunk, fresh CRCs
+    /// and, unless `keep_trailing` is set, no bytes after IEND
+    pub fn sanitize_for_web(&mut self, keep_trailing: bool) {
+        self.retain_chunks(|chunk| chunk.chunk_type().is_critical());
+        self.coalesce_idat();
+        self.canonicalize();
+        self.recompute_crcs();
+        if !keep_trailing {
+            self.trailing.clear();
+        }
+    }
+
+    pub fn recompute_crcs(&mut self) {
+        self.chunks.iter_mut().for_each(Chunk::recompute_crc);
+    }
+
+    fn signature(&self) -> &[u8; 8] {
         &Png::STANDARD_HEADER
     }
 
+    /// The image header parsed from the first IHDR chunk
+    pub fn header(&self) -> anyhow::Result<Ihdr> {
+        let ihdr = self.chunk_by_type("IHDR").ok_or_else(|| PngError::ChunkTypeNotFound("IHDR".to_string()))?;
+        Ok(Ihdr::try_from(ihdr.data())?)
+    }
+
     pub fn chunks(&self) -> &[Chunk] {
         &self.chunks
     }
 
+    /// Data of each chunk in order
+    pub fn iter_data(&self) -> impl Iterator<Item = &[u8]> {
+        self.chunks.iter().map(Chunk::data)
+    }
+
+    pub fn first_chunk(&self) -> Option<&Chunk> {
+        self.chunks.first()
+    }
+
+    pub fn last_chunk(&self) -> Option<&Chunk> {
+        self.chunks.last()
+    }
+
+    pub fn has_valid_signature(&self) -> bool {
+        self.valid_signature
+    }
+
     pub fn chunk_by_type(&self, chunk_type: &str) -> Option<&Chunk> {
-        let chunk_type = ChunkType::from_str(chunk_type);
-        if chunk_type.is_err() {
+        self.chunk_by_type_at(chunk_type, 0)
+    }
+
+    /// The `n`-th chunk of `chunk_type`, counting from 0
+    pub fn chunk_by_type_at(&self, chunk_type: &str, n: usize) -> Option<&Chunk> {
+        let chunk_type = ChunkType::from_str(chunk_type).ok()?;
+        self.chunks.iter().filter(|chunk| *chunk.chunk_type() == chunk_type).nth(n)
+    }
+
+    /// Whether any chunk of `chunk_type` has data satisfying `pred`, an unparsable type matches nothing
+    pub fn contains_message_matching<F: Fn(&[u8]) -> bool>(&self, chunk_type: &str, pred: F) -> bool {
+        let Ok(chunk_type) = ChunkType::from_str(chunk_type) else {
+            return false;
+        };
+        self.chunks.iter().any(|chunk| *chunk.chunk_type() == chunk_type && pred(chunk.data()))
+    }
+
+    /// Chunks added, removed and modified going from `self` to `other`, matching up the longest common run of equal chunks
+    pub fn diff_against(&self, other: &Png) -> PngDiff {
+        let (before, after) = (&self.chunks, &other.chunks);
+        // lcs[i][j] is the longest common subsequence of before[i..] and after[j..]
+        let mut lcs = vec![vec![0usize; after.len() + 1]; before.len() + 1];
+        for i in (0..before.len()).rev() {
+            for j in (0..after.len()).rev() {
+                lcs[i][j] = if before[i] == after[j] { lcs[i + 1][j + 1] + 1 } else { lcs[i + 1][j].max(lcs[i][j + 1]) };
+            }
+        }
+
+        let (mut i, mut j, mut changes) = (0, 0, Vec::new());
+        while i < before.len() || j < after.len() {
+            if i < before.len() && j < after.len() && before[i] == after[j] {
+                i += 1;
+                j += 1;
+            } else if i < before.len() && j < after.len() && before[i].chunk_type() == after[j].chunk_type() && lcs[i + 1][j + 1] == lcs[i][j] {
+                changes.push(ChunkChange::Modified {
+                    index: i,
+                    chunk_type: before[i].chunk_type().clone(),
+                    before: before[i].length(),
+                    after: after[j].length(),
+                });
+                i += 1;
+                j += 1;
+            } else if j == after.len() || (i < before.len() && lcs[i + 1][j] >= lcs[i][j + 1]) {
+                changes.push(ChunkChange::Removed { index: i, chunk_type: before[i].chunk_type().clone(), length: before[i].length() });
+                i += 1;
+            } else {
+                changes.push(ChunkChange::Added { index: j, chunk_type: after[j].chunk_type().clone(), length: after[j].length() });
+                j += 1;
+            }
+        }
+        PngDiff { changes }
+    }
+
+    pub fn chunk_summaries(&self) -> Vec<ChunkSummary> {
+        self.chunks.iter().map(|chunk| {
+            let chunk_type = chunk.chunk_type();
+            ChunkSummary {
+                chunk_type: chunk_type.to_string(),
+                length: chunk.length(),
+                crc: chunk.stored_crc(),
+                critical: chunk_type.is_critical(),
+                public: chunk_type.is_public(),
+                reserved_bit_valid: chunk_type.is_reserved_bit_valid(),
+                safe_to_copy: chunk_type.is_safe_to_copy(),
+            }
+        }).collect()
+    }
+
+    /// Type and recomputed CRC of every chunk in order, a manifest that can be diffed across file versions
+    pub fn checksum_all(&self) -> Vec<(ChunkType, u32)> {
+        self.chunks.iter().map(|chunk| (chunk.chunk_type().clone(), chunk.crc())).collect()
+    }
+
+    /// Data of every IDAT chunk concatenated in order, the zlib stream holding the pixels
+    pub fn idat_stream(&self) -> Vec<u8> {
+        self.data_of_type("IDAT")
+    }
+
+    pub fn critical_chunks(&self) -> impl Iterator<Item = &Chunk> {
+        self.chunks.iter().filter(|chunk| chunk.chunk_type().is_critical())
+    }
+
+    pub fn ancillary_chunks(&self) -> impl Iterator<Item = &Chunk> {
+        self.chunks.iter().filter(|chunk| !chunk.chunk_type().is_critical())
+    }
+
+    pub fn critical_count(&self) -> usize {
+        self.critical_chunks().count()
+    }
+
+    pub fn ancillary_count(&self) -> usize {
+        self.ancillary_chunks().count()
+    }
+
+    /// Compares the raw image size implied by IHDR with the inflated IDAT stream, `Some` describes a mismatch
+    #[cfg(feature = "compression")]
+    pub fn validate_ihdr_matches_data(&self) -> anyhow::Result<Option<String>> {
+        let ihdr = self.chunk_by_type("IHDR").ok_or_else(|| PngError::ChunkTypeNotFound("IHDR".to_string()))?;
+        let expected = match Ihdr::try_from(ihdr.data()).map(|header| header.filtered_size()) {
+            Ok(Some(expected)) => expected,
+            Ok(None) => return Ok(Some("IHDR implies more image data than can be addressed".to_string())),
+            Err(err) => return Ok(Some(format!("IHDR is invalid: {}", err))),
+        };
+        // One byte past the expected size is enough to tell a mismatch, without inflating a crafted stream further
+        let actual = match crate::zlib::decompress_limited(&self.idat_stream(), expected.saturating_add(1)) {
+            Err(err) if matches!(err.downcast_ref(), Some(crate::zlib::ZlibError::OutputTooLarge(_))) => {
+                return Ok(Some(format!("IHDR implies {} bytes of image data but IDAT holds more", expected)));
+            }
+            result => result?.len(),
+        };
+        Ok((actual != expected).then(|| format!("IHDR implies {} bytes of image data but IDAT holds {}", expected, actual)))
+    }
+
+    /// Serialized bytes of critical and ancillary chunks, as `(critical, ancillary)`
+    pub fn count_bytes_by_kind(&self) -> (usize, usize) {
+        self.chunks.iter().fold((0, 0), |(critical, ancillary), chunk| {
+            if chunk.chunk_type().is_critical() {
+                (critical + chunk.total_size(), ancillary)
+            } else {
+                (critical, ancillary + chunk.total_size())
+            }
+        })
+    }
+
+    /// Byte offset in the serialized file where the chunk at `index` begins
+    pub fn byte_offset_of(&self, index: usize) -> Option<usize> {
+        if index >= self.chunks.len() {
             return None;
         }
-        let chunk_type_res = chunk_type.unwrap();
-        self.chunks.iter().find(|chunk| *chunk.chunk_type() == chunk_type_res)
+        let prior: usize = self.chunks[..index].iter().map(Chunk::total_size).sum();
+        Some(Png::STANDARD_HEADER.len() + prior)
     }
 
-    pub fn as_bytes(&self) -> Vec<u8> {
-        let chunks_bytes: Vec<u8> = self.chunks.iter()
-            .flat_map(|chunk| chunk.as_bytes())
-            .collect();
-        Png::STANDARD_HEADER.iter()
-            .chain(chunks_bytes.iter())
-            .copied()
+    pub fn first_idat_index(&self) -> Option<usize> {
+        self.position_of(|chunk| *chunk.chunk_type() == *b"IDAT")
+    }
+
+    pub fn first_ancillary_index(&self) -> Option<usize> {
+        self.position_of(|chunk| !chunk.chunk_type().is_critical())
+    }
+
+    /// Index of the first chunk matching `pred`, like `Iterator::position`
+    pub fn position_of<F: Fn(&Chunk) -> bool>(&self, pred: F) -> Option<usize> {
+        self.chunks.iter().position(pred)
+    }
+
+    pub fn find_by_crc(&self, crc: u32) -> Option<(usize, &Chunk)> {
+        let idx = self.position_of(|chunk| chunk.crc() == crc)?;
+        Some((idx, &self.chunks[idx]))
+    }
+
+    /// Summed data length of every chunk of `chunk_type`, like `data_of_type(..).len()` without allocating
+    pub fn bytes_len_of_type(&self, chunk_type: &str) -> u64 {
+        let Ok(chunk_type) = ChunkType::from_str(chunk_type) else {
+            return 0;
+        };
+        self.chunks.iter()
+            .filter(|chunk| *chunk.chunk_type() == chunk_type)
+            .map(|chunk| chunk.length() as u64)
+            .sum()
+    }
+
+    pub fn data_of_type(&self, chunk_type: &str) -> Vec<u8> {
+        self.join_type_data(chunk_type, None)
+    }
+
+    /// Data of every chunk of `chunk_type` in order, with `sep` between consecutive chunks to keep their boundaries
+    pub fn join_type_data(&self, chunk_type: &str, sep: Option<u8>) -> Vec<u8> {
+        let chunk_type = match ChunkType::from_str(chunk_type) {
+            Ok(chunk_type) => chunk_type,
+            Err(_) => return Vec::new(),
+        };
+        let mut joined = Vec::new();
+        for (i, chunk) in self.chunks.iter().filter(|chunk| *chunk.chunk_type() == chunk_type).enumerate() {
+            if let Some(sep) = sep.filter(|_| i > 0) {
+                joined.push(sep);
+            }
+            joined.extend_from_slice(chunk.data());
+        }
+        joined
+    }
+
+    /// Stores `metadata` as compact JSON, replacing any previous metadata chunk
+    pub fn set_metadata(&mut self, metadata: &Value) -> anyhow::Result<()> {
+        let chunk_type = ChunkType::from_str(Png::METADATA_CHUNK_TYPE)?;
+        self.chunks.retain(|chunk| *chunk.chunk_type() != chunk_type);
+        self.append_chunk_indexed(Chunk::new(chunk_type, metadata.to_string().into_bytes()));
+        Ok(())
+    }
+
+    pub fn metadata(&self) -> anyhow::Result<Option<Value>> {
+        match self.chunk_by_type(Png::METADATA_CHUNK_TYPE) {
+            Some(chunk) => Ok(Some(Value::parse(&chunk.data_as_string()?)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Writes the data of every ancillary chunk, or of every chunk of `chunk_type`, to `<index>_<type>.bin` files in `dir`
+    pub fn extract_to_files(&self, dir: &Path, chunk_type: Option<&str>) -> anyhow::Result<Vec<PathBuf>> {
+        let chunk_type = chunk_type.map(ChunkType::from_str).transpose()?;
+        fs::create_dir_all(dir)?;
+
+        let mut written = Vec::new();
+        for (idx, chunk) in self.chunks.iter().enumerate() {
+            let selected = match &chunk_type {
+                Some(chunk_type) => chunk.chunk_type() == chunk_type,
+                None => !chunk.chunk_type().is_critical(),
+            };
+            if selected {
+                let path = dir.join(format!("{}_{}.bin", idx, chunk.chunk_type()));
+                fs::write(&path, chunk.data())?;
+                written.push(path);
+            }
+        }
+        Ok(written)
+    }
+
+    pub fn append_text(&mut self, keyword: &str, text: &str) -> anyhow::Result<()> {
+        self.append_chunk_indexed(Chunk::new_text(keyword, text)?);
+        Ok(())
+    }
+
+    /// Keyword and text of every well-formed tEXt chunk, in file order
+    pub fn text_entries(&self) -> Vec<(String, String)> {
+        self.chunks.iter().filter_map(Chunk::text_entry).collect()
+    }
+
+    /// Compresses `text` into a zTXt chunk under `keyword`, appended before IEND
+    #[cfg(feature = "compression")]
+    pub fn append_ztxt(&mut self, keyword: &str, text: &str) -> anyhow::Result<()> {
+        self.append_chunk_indexed(Chunk::new_ztxt(keyword, text)?);
+        Ok(())
+    }
+
+    /// Keyword and decompressed text of every zTXt chunk, in file order
+    #[cfg(feature = "compression")]
+    pub fn ztxt_entries(&self) -> anyhow::Result<Vec<(String, String)>> {
+        self.chunks.iter().filter_map(|chunk| chunk.ztxt_entry().transpose()).collect()
+    }
+
+    /// Appends an iTXt chunk before IEND
+    pub fn append_itxt(&mut self, entry: &InternationalText) -> anyhow::Result<()> {
+        self.append_chunk_indexed(Chunk::new_itxt(entry)?);
+        Ok(())
+    }
+
+    /// Fields of every iTXt chunk, in file order
+    pub fn itxt_entries(&self) -> anyhow::Result<Vec<InternationalText>> {
+        self.chunks.iter().filter_map(|chunk| chunk.itxt_entry().transpose()).collect()
+    }
+
+    /// Text of the first tEXt chunk stored under `keyword`
+    pub fn text_by_keyword(&self, keyword: &str) -> Option<String> {
+        self.text_entries()
+            .into_iter()
+            .find(|(key, _)| key == keyword)
+            .map(|(_, text)| text)
+    }
+
+    /// Decompressed text of the first zTXt chunk stored under `keyword`
+    #[cfg(feature = "compression")]
+    pub fn ztxt_by_keyword(&self, keyword: &str) -> anyhow::Result<Option<String>> {
+        Ok(self.ztxt_entries()?
+            .into_iter()
+            .find(|(key, _)| key == keyword)
+            .map(|(_, text)| text))
+    }
+
+    /// Compresses `payload` and appends it before IEND as chunks of `chunk_type`, each framed by its big-endian
+    /// u16 sequence number, the u16 piece count and a flags byte, returns how many chunks were added.
+    /// With `integrity` the first chunk also stores the CRC-32 of the whole payload after its flags
+    #[cfg(feature = "compression")]
+    pub fn hide_payload(&mut self, chunk_type: &str, payload: &[u8], integrity: bool) -> anyhow::Result<usize> {
+        let chunk_type = ChunkType::from_str(chunk_type)?;
+        let compressed = crate::zlib::compress(payload);
+        let pieces: Vec<&[u8]> = compressed.chunks(Png::PAYLOAD_PIECE_SIZE).collect();
+        let total = u16::try_from(pieces.len()).map_err(|_| PngError::PayloadTooLarge(compressed.len()))?;
+        for (seq, piece) in pieces.iter().enumerate() {
+            let mut data = Vec::with_capacity(piece.len() + 9);
+            data.extend_from_slice(&(seq as u16).to_be_bytes());
+            data.extend_from_slice(&total.to_be_bytes());
+            data.push(if integrity { PAYLOAD_INTEGRITY_FLAG } else { 0 });
+            if integrity && seq == 0 {
+                data.extend_from_slice(&payload_crc(payload).to_be_bytes());
+            }
+            data.extend_from_slice(piece);
+            self.append_chunk_indexed(Chunk::new(chunk_type.clone(), data));
+        }
+        Ok(pieces.len())
+    }
+
+    /// Reassembles and decompresses a payload stored by `hide_payload`, the pieces may be in any order.
+    /// A payload stored with integrity is only returned if its CRC-32 matches end to end
+    #[cfg(feature = "compression")]
+    pub fn reveal_payload(&self, chunk_type: &str) -> anyhow::Result<Vec<u8>> {
+        let chunk_type = ChunkType::from_str(chunk_type)?;
+        let mut pieces = self.chunks.iter()
+            .filter(|chunk| *chunk.chunk_type() == chunk_type)
+            .enumerate()
+            .map(|(idx, chunk)| match chunk.data() {
+                [s0, s1, t0, t1, flags, piece @ ..] => Ok((u16::from_be_bytes([*s0, *s1]), u16::from_be_bytes([*t0, *t1]), *flags, piece)),
+                _ => Err(PngError::InvalidPayloadPiece(idx)),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        if pieces.is_empty() {
+            bail!(PngError::ChunkTypeNotFound(chunk_type.to_string()));
+        }
+        pieces.sort_by_key(|(seq, _, _, _)| *seq);
+
+        let mut expected_crc = None;
+        if pieces[0].2 & PAYLOAD_INTEGRITY_FLAG != 0 {
+            let [c0, c1, c2, c3, piece @ ..] = pieces[0].3 else {
+                bail!(PngError::InvalidPayloadPiece(0));
+            };
+            expected_crc = Some(u32::from_be_bytes([*c0, *c1, *c2, *c3]));
+            pieces[0].3 = piece;
+        }
+        let mut compressed = Vec::new();
+        for (idx, (seq, total, _, piece)) in pieces.iter().enumerate() {
+            if *seq as usize != idx || *total as usize != pieces.len() {
+                bail!(PngError::InvalidPayloadPiece(idx));
+            }
+            compressed.extend_from_slice(piece);
+        }
+        let payload = crate::zlib::decompress(&compressed)?;
+        if let Some(expected) = expected_crc.filter(|expected| *expected != payload_crc(&payload)) {
+            bail!(PngError::PayloadCrcMismatch(payload_crc(&payload), expected));
+        }
+        Ok(payload)
+    }
+
+    /// Appends `payload` before IEND as chunks of `chunk_type` holding at most `piece_size` payload bytes each, framed by
+    /// their big-endian u32 sequence number and piece count, returns how many chunks were added
+    pub fn append_split(&mut self, chunk_type: &str, payload: &[u8], piece_size: usize) -> anyhow::Result<usize> {
+        if !(1..=Png::MAX_PIECE_SIZE).contains(&piece_size) {
+            bail!(PngError::InvalidPieceSize(piece_size));
+        }
+        let chunk_type = ChunkType::from_str(chunk_type)?;
+        // An empty payload still gets a piece so it can be told apart from a missing one
+        let pieces: Vec<&[u8]> = if payload.is_empty() { vec![payload] } else { payload.chunks(piece_size).collect() };
+        let total = u32::try_from(pieces.len()).map_err(|_| PngError::PayloadTooLarge(payload.len()))?;
+        for (seq, piece) in pieces.iter().enumerate() {
+            let mut data = Vec::with_capacity(piece.len() + SPLIT_FRAME_LEN);
+            data.extend_from_slice(&(seq as u32).to_be_bytes());
+            data.extend_from_slice(&total.to_be_bytes());
+            data.extend_from_slice(piece);
+            self.append_chunk_indexed(Chunk::new(chunk_type.clone(), data));
+        }
+        Ok(pieces.len())
+    }
+
+    /// Reassembles a payload stored by `append_split`, the pieces may be in any order
+    pub fn join_split(&self, chunk_type: &str) -> anyhow::Result<Vec<u8>> {
+        let chunk_type = ChunkType::from_str(chunk_type)?;
+        let mut pieces = self.chunks.iter()
+            .filter(|chunk| *chunk.chunk_type() == chunk_type)
+            .enumerate()
+            .map(|(idx, chunk)| match chunk.data() {
+                [s0, s1, s2, s3, t0, t1, t2, t3, piece @ ..] =>
+                    Ok((u32::from_be_bytes([*s0, *s1, *s2, *s3]), u32::from_be_bytes([*t0, *t1, *t2, *t3]), piece)),
+                _ => Err(PngError::InvalidPayloadPiece(idx)),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        if pieces.is_empty() {
+            bail!(PngError::ChunkTypeNotFound(chunk_type.to_string()));
+        }
+        pieces.sort_by_key(|(seq, _, _)| *seq);
+
+        let mut payload = Vec::with_capacity(pieces.iter().map(|(_, _, piece)| piece.len()).sum());
+        for (idx, (seq, _, piece)) in pieces.iter().enumerate() {
+            if *seq as usize != idx {
+                bail!(PngError::InvalidPayloadPiece(idx));
+            }
+            payload.extend_from_slice(piece);
+        }
+        // Every piece is in sequence, so a disagreeing count means pieces are missing from the end
+        if let Some((_, total, _)) = pieces.iter().find(|(_, total, _)| *total as usize != pieces.len()) {
+            bail!(PngError::InvalidPayloadPiece(pieces.len().min(*total as usize)));
+        }
+        Ok(payload)
+    }
+
+    pub fn chunks_with_type_prefix(&self, prefix: &str) -> Vec<&Chunk> {
+        self.chunks
+            .iter()
+            .filter(|chunk| chunk.chunk_type().to_string().starts_with(prefix))
             .collect()
     }
+
+    /// Bytes following the IEND chunk, they are not part of any chunk
+    pub fn trailing_bytes(&self) -> &[u8] {
+        &self.trailing
+    }
+
+    pub fn set_trailing_bytes(&mut self, bytes: Vec<u8>) {
+        self.trailing = bytes;
+    }
+
+    /// The serialized file as standard base64, for text-only transports
+    pub fn to_base64(&self) -> String {
+        base64::encode(&self.as_bytes())
+    }
+
+    pub fn from_base64(s: &str) -> anyhow::Result<Png> {
+        Png::try_from(base64::decode(s)?.as_slice())
+    }
+
+    pub fn as_bytes(&self) -> Vec<u8> {
+        let size = self.signature().len() + self.chunks.iter().map(Chunk::total_size).sum::<usize>() + self.trailing.len();
+        let mut bytes = Vec::with_capacity(size);
+        self.write_to(&mut bytes).expect("writing to a Vec never fails");
+        bytes
+    }
+
+    /// Writes the same bytes as `as_bytes` field by field, without assembling the whole file in memory
+    pub fn write_to<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        w.write_all(self.signature())?;
+        for chunk in &self.chunks {
+            w.write_all(&chunk.length().to_be_bytes())?;
+            w.write_all(&chunk.chunk_type().bytes())?;
+            w.write_all(chunk.data())?;
+            w.write_all(&chunk.stored_crc().to_be_bytes())?;
+        }
+        w.write_all(&self.trailing)
+    }
 }
 
 impl TryFrom<&[u8]> for Png {
     type Error = anyhow::Error;
 
     fn try_from(value: &[u8]) -> anyhow::Result<Self> {
+        Png::parse(value, false)
+    }
+}
+
+impl Png {
+    fn parse(value: &[u8], lenient: bool) -> anyhow::Result<Png> {
         let value = value.to_vec();
-        let header_bytes = value.get(..8).ok_or(PngError::InvalidHeaderLength)?;
-        if header_bytes != Png::STANDARD_HEADER {
+        let header_bytes = value.get(..8).ok_or(PngError::FileTooSmall(value.len()))?;
+        let valid_signature = header_bytes == Png::STANDARD_HEADER;
+        if !valid_signature && !lenient {
             bail!(PngError::InvalidHeaderContent);
         }
 
+        let mut png = Png::parse_chunks(&value, 8, lenient, false)?;
+        png.valid_signature = valid_signature;
+        Ok(png)
+    }
+
+    // `seen_end` is set when the chunks before `value` already ended with IEND
+    fn parse_chunks(value: &[u8], mut start_idx: usize, lenient: bool, mut seen_end: bool) -> anyhow::Result<Png> {
         let mut chunks = Vec::new();
-        let mut start_idx = 8;
         while start_idx < value.len() {
-            let length_bytes = value.get(start_idx..start_idx + 4).ok_or(PngError::InvalidChunkLength)?;
-            let length_u32: Vec<u32> = length_bytes.iter().map(|x| *x as u32).collect();
-            let length = (length_u32[0] << 24) | (length_u32[1] << 16) | (length_u32[2] << 8) | length_u32[3];
-
-            // 4 for length bytes, 4 for chunk type bytes, 4 for crc bytes, length for data bytes
-            let end = start_idx + 12 + length as usize;
-            let chunk_value_bytes = value.get(start_idx..end)
-                .ok_or(ChunkError::InvalidLength)?;
-            chunks.push(Chunk::try_from(chunk_value_bytes)?);
-            start_idx = end;
+            match Png::parse_chunk(value, start_idx, lenient) {
+                Ok((chunk, _)) if seen_end && chunk.is_truncated() => break,
+                Ok((chunk, end)) => {
+                    seen_end |= *chunk.chunk_type() == *b"IEND";
+                    chunks.push(chunk);
+                    start_idx = end;
+                }
+                // Whatever does not parse as a chunk after IEND is kept as trailing bytes
+                Err(_) if seen_end => break,
+                Err(err) => return Err(err),
+            }
         }
 
-        Ok(Png::from_chunks(chunks))
+        let trailing = value[start_idx..].to_vec();
+        Ok(Png { chunks, valid_signature: true, trailing })
+    }
+
+    // Parses the chunk starting at `start_idx` and returns it with the index right after it
+    fn parse_chunk(value: &[u8], start_idx: usize, lenient: bool) -> anyhow::Result<(Chunk, usize)> {
+        value.get(start_idx..start_idx + 4).ok_or(PngError::InvalidChunkLength(start_idx))?;
+        let (chunk, consumed) = Chunk::parse_prefix(&value[start_idx..], !lenient)
+            .map_err(|source| PngError::InvalidChunk { offset: start_idx, source })?;
+        Ok((chunk, start_idx + consumed))
     }
 }
 
@@ -115,6 +1032,32 @@ impl Display for Png {
     }
 }
 
+// Sequence number and piece count framing every chunk written by `append_split`
+const SPLIT_FRAME_LEN: usize = 8;
+
+// Flags byte bit marking a payload whose first chunk stores the CRC-32 of the whole payload
+#[cfg(feature = "compression")]
+const PAYLOAD_INTEGRITY_FLAG: u8 = 1;
+
+#[cfg(feature = "compression")]
+fn payload_crc(payload: &[u8]) -> u32 {
+    crc::Crc::<u32>::new(&crc::CRC_32_ISO_HDLC).checksum(payload)
+}
+
+// Place of a chunk type in the canonical order, types without a fixed place go before or after IDAT per `before_idat`
+fn canonical_rank(chunk_type: &ChunkType, before_idat: bool) -> u8 {
+    match &chunk_type.bytes() {
+        b"IHDR" => 0,
+        b"cHRM" | b"gAMA" | b"iCCP" | b"sBIT" | b"sRGB" | b"cICP" => 1,
+        b"PLTE" => 2,
+        b"tRNS" | b"bKGD" | b"hIST" | b"pHYs" | b"sPLT" | b"eXIf" | b"acTL" => 3,
+        b"IDAT" => 4,
+        b"IEND" => 6,
+        _ if before_idat => 3,
+        _ => 5,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use std::convert::TryFrom;
@@ -126,13 +1069,11 @@ mod tests {
     use super::*;
 
     fn testing_chunks() -> Vec<Chunk> {
-        let mut chunks = Vec::new();
-
-        chunks.push(chunk_from_strings("FrSt", "I am the first chunk").unwrap());
-        chunks.push(chunk_from_strings("miDl", "I am another chunk").unwrap());
-        chunks.push(chunk_from_strings("LASt", "I am the last chunk").unwrap());
-
-        chunks
+        vec![
+            chunk_from_strings("FrSt", "I am the first chunk").unwrap(),
+            chunk_from_strings("miDl", "I am another chunk").unwrap(),
+            chunk_from_strings("LASt", "I am the last chunk").unwrap(),
+        ]
     }
 
     fn testing_png() -> Png {
@@ -191,6 +1132,45 @@ mod tests {
         assert!(png.is_err());
     }
 
+    #[test]
+    fn test_peek_signature() {
+        let mut cursor = io::Cursor::new(&PNG_FILE[..]);
+        assert!(Png::peek_signature(&mut cursor).unwrap());
+        assert_eq!(cursor.position(), 8);
+
+        assert!(!Png::peek_signature(&mut io::Cursor::new(&PNG_FILE[1..])).unwrap());
+        assert!(!Png::peek_signature(&mut io::Cursor::new(&PNG_FILE[..4])).unwrap());
+    }
+
+    #[test]
+    fn test_from_reader() {
+        let png = Png::from_reader(&mut io::Cursor::new(&PNG_FILE[..])).unwrap();
+        assert_eq!(png, Png::try_from(&PNG_FILE[..]).unwrap());
+
+        let mut with_trailing = png.clone();
+        with_trailing.set_trailing_bytes(b"after the end".to_vec());
+        let bytes = with_trailing.as_bytes();
+        assert_eq!(Png::from_reader(&mut bytes.as_slice()).unwrap(), with_trailing);
+
+        let unterminated = testing_png().as_bytes();
+        assert_eq!(Png::from_reader(&mut unterminated.as_slice()).unwrap(), testing_png());
+        assert!(Png::from_reader(&mut &PNG_FILE[..4]).is_err());
+        assert!(Png::from_reader(&mut &PNG_FILE[..PNG_FILE.len() - 3]).is_err());
+        assert!(Png::from_reader(&mut &PNG_FILE[1..]).is_err());
+    }
+
+    #[test]
+    fn test_file_too_small() {
+        for bytes in [&[][..], &PNG_FILE[..4]] {
+            for result in [Png::try_from(bytes), Png::from_bytes_lenient(bytes)] {
+                let err = result.unwrap_err();
+                assert!(matches!(err.downcast_ref::<PngError>(), Some(PngError::FileTooSmall(n)) if *n == bytes.len()));
+            }
+        }
+        let err = Png::try_from(&PNG_FILE[..4]).unwrap_err();
+        assert_eq!(err.to_string(), "File is too small to be a Png, expected at least 8 bytes for the signature but got 4");
+    }
+
     #[test]
     fn test_invalid_chunk() {
         let mut chunk_bytes: Vec<u8> = testing_chunks()
@@ -221,6 +1201,22 @@ mod tests {
         assert_eq!(chunks.len(), 3);
     }
 
+    #[test]
+    fn test_iter_data() {
+        let png = testing_png();
+        let data: Vec<&[u8]> = png.iter_data().collect();
+        assert_eq!(data, [&b"I am the first chunk"[..], b"I am another chunk", b"I am the last chunk"]);
+        assert!(png.iter_data().zip(png.chunks()).all(|(data, chunk)| data == chunk.data()));
+    }
+
+    #[test]
+    fn test_first_and_last_chunk() {
+        let png = testing_png();
+        assert_eq!(&png.first_chunk().unwrap().chunk_type().to_string(), "FrSt");
+        assert_eq!(&png.last_chunk().unwrap().chunk_type().to_string(), "LASt");
+        assert!(Png::from_chunks(Vec::new()).first_chunk().is_none());
+    }
+
     #[test]
     fn test_chunk_by_type() {
         let png = testing_png();
@@ -229,6 +1225,420 @@ mod tests {
         assert_eq!(&chunk.data_as_string().unwrap(), "I am the first chunk");
     }
 
+    #[test]
+    fn test_chunk_by_type_at() {
+        let mut png = testing_png();
+        for message in ["first", "second", "third"] {
+            png.append_chunk(chunk_from_strings("ruSt", message).unwrap());
+        }
+        assert_eq!(png.chunk_by_type_at("ruSt", 1).unwrap().data(), b"second");
+        assert_eq!(png.chunk_by_type_at("ruSt", 0).unwrap().data(), b"first");
+        assert!(png.chunk_by_type_at("ruSt", 3).is_none());
+    }
+
+    #[test]
+    fn test_contains_message_matching() {
+        let png = testing_png();
+        assert!(png.contains_message_matching("miDl", |data| data.starts_with(b"I am another")));
+        assert!(!png.contains_message_matching("FrSt", |data| data.starts_with(b"I am another")));
+        assert!(!png.contains_message_matching("ruSt", |_| true));
+        assert!(!png.contains_message_matching("bad", |_| true));
+    }
+
+    #[test]
+    fn test_diff_against() {
+        let before = testing_png();
+        let mut after = before.clone();
+        after.append_chunk(chunk_from_strings("ruSt", "hidden").unwrap());
+
+        let diff = before.diff_against(&after);
+        assert_eq!(diff.changes, [ChunkChange::Added { index: 3, chunk_type: ChunkType::from_str("ruSt").unwrap(), length: 6 }]);
+        assert!(before.diff_against(&before).is_empty());
+        assert!(matches!(after.diff_against(&before).changes[..], [ChunkChange::Removed { index: 3, .. }]));
+    }
+
+    #[test]
+    fn test_chunk_summaries() {
+        // The fixture is IHDR, sRGB, gAMA, pHYs, IDAT, RuSt, IEND
+        let png = Png::try_from(&PNG_FILE[..]).unwrap();
+        let summaries = png.chunk_summaries();
+        let types: Vec<&str> = summaries.iter().map(|summary| summary.chunk_type.as_str()).collect();
+        assert_eq!(types, ["IHDR", "sRGB", "gAMA", "pHYs", "IDAT", "RuSt", "IEND"]);
+        assert_eq!(summaries[0].length, 13);
+        assert_eq!(summaries[0].crc, png.chunks()[0].crc());
+
+        let rust = &summaries[5];
+        assert!(rust.critical && !rust.public && rust.reserved_bit_valid && rust.safe_to_copy);
+        assert!(!summaries[1].critical && summaries[1].public && !summaries[1].safe_to_copy);
+    }
+
+    #[test]
+    fn test_find_by_crc() {
+        let png = testing_png();
+        let crc = png.chunks()[1].crc();
+        let (idx, chunk) = png.find_by_crc(crc).unwrap();
+        assert_eq!(idx, 1);
+        assert_eq!(&chunk.chunk_type().to_string(), "miDl");
+        assert!(png.find_by_crc(crc.wrapping_add(1)).is_none());
+    }
+
+    #[test]
+    fn test_checksum_all() {
+        let png = Png::try_from(&PNG_FILE[..]).unwrap();
+        let manifest = png.checksum_all();
+        assert_eq!(manifest.len(), png.chunks().len());
+        assert_eq!(manifest[0], (ChunkType::from_str("IHDR").unwrap(), png.chunks()[0].crc()));
+    }
+
+    #[test]
+    fn test_join_type_data() {
+        let mut png = testing_png();
+        png.append_chunk(chunk_from_strings("ruSt", "first").unwrap());
+        png.append_chunk(chunk_from_strings("ruSt", "second").unwrap());
+        assert_eq!(png.join_type_data("ruSt", Some(0)), b"first\0second".to_vec());
+        assert_eq!(png.join_type_data("ruSt", None), b"firstsecond".to_vec());
+        assert_eq!(png.join_type_data("miDl", Some(0)), b"I am another chunk".to_vec());
+    }
+
+    #[test]
+    fn test_idat_stream() {
+        let png = Png::from_chunks(vec![
+            chunk_from_strings("IHDR", "header").unwrap(),
+            chunk_from_strings("IDAT", "first part ").unwrap(),
+            chunk_from_strings("IDAT", "second part").unwrap(),
+            chunk_from_strings("IEND", "").unwrap(),
+        ]);
+        let idat_lengths: u32 = png.chunks()[1..3].iter().map(Chunk::length).sum();
+
+        let stream = png.idat_stream();
+        assert_eq!(stream.len(), idat_lengths as usize);
+        assert_eq!(stream, b"first part second part");
+    }
+
+    #[test]
+    fn test_critical_and_ancillary_count() {
+        // FrSt and LASt are critical, miDl is ancillary
+        let png = testing_png();
+        assert_eq!(png.critical_count(), 2);
+        assert_eq!(png.ancillary_count(), 1);
+        assert_eq!(&png.ancillary_chunks().next().unwrap().chunk_type().to_string(), "miDl");
+    }
+
+    #[test]
+    #[cfg(feature = "compression")]
+    fn test_validate_ihdr_matches_data() {
+        let mut png = Png::try_from(&PNG_FILE[..]).unwrap();
+        assert_eq!(png.validate_ihdr_matches_data().unwrap(), None);
+
+        // Double the width stored in IHDR
+        let mut ihdr = png.chunk_by_type("IHDR").unwrap().data().to_vec();
+        let width = u32::from_be_bytes(ihdr[..4].try_into().unwrap());
+        ihdr[..4].copy_from_slice(&(width * 2).to_be_bytes());
+        png.replace_data_of_type("IHDR", ihdr).unwrap();
+
+        let warning = png.validate_ihdr_matches_data().unwrap().unwrap();
+        assert!(warning.starts_with("IHDR implies"), "{}", warning);
+
+        // Valid but enormous dimensions at 16-bit RGBA
+        png.replace_data_of_type("IHDR", vec![0x7F, 0xFF, 0xFF, 0xFF, 0x7F, 0xFF, 0xFF, 0xFF, 16, 6, 0, 0, 0]).unwrap();
+        let warning = png.validate_ihdr_matches_data().unwrap().unwrap();
+        assert_eq!(warning, "IHDR implies more image data than can be addressed");
+    }
+
+    #[test]
+    fn test_header() {
+        let png = Png::try_from(&PNG_FILE[..]).unwrap();
+        let header = png.header().unwrap();
+        assert_eq!((header.width, header.height, header.bit_depth, header.color_type), (50, 50, 8, 6));
+        assert!(!header.is_interlaced());
+
+        let png = Png::from_chunks(testing_chunks());
+        assert!(matches!(png.header().unwrap_err().downcast_ref(), Some(PngError::ChunkTypeNotFound(_))));
+    }
+
+    #[test]
+    fn test_count_bytes_by_kind() {
+        // FrSt and LASt are critical, miDl is ancillary
+        let png = testing_png();
+        assert_eq!(png.count_bytes_by_kind(), (20 + 12 + 19 + 12, 18 + 12));
+    }
+
+    #[test]
+    fn test_byte_offset_of() {
+        let png = testing_png();
+        assert_eq!(png.byte_offset_of(0), Some(8));
+        assert_eq!(png.byte_offset_of(1), Some(8 + 12 + png.chunks()[0].length() as usize));
+        assert_eq!(png.byte_offset_of(3), None);
+
+        let bytes = png.as_bytes();
+        let offset = png.byte_offset_of(2).unwrap();
+        assert_eq!(&bytes[offset + 4..offset + 8], b"LASt");
+    }
+
+    #[test]
+    fn test_first_idat_and_ancillary_index() {
+        // The fixture is IHDR, sRGB, gAMA, pHYs, IDAT, RuSt, IEND
+        let png = Png::try_from(&PNG_FILE[..]).unwrap();
+        assert_eq!(png.first_idat_index(), Some(4));
+        assert_eq!(png.first_ancillary_index(), Some(1));
+        assert_eq!(testing_png().first_idat_index(), None);
+    }
+
+    #[test]
+    fn test_position_of() {
+        let mut png = testing_png();
+        png.append_chunk(chunk_from_strings("ruSt", "a much longer message").unwrap());
+        assert_eq!(png.position_of(|chunk| chunk.length() > 18), Some(0));
+        assert_eq!(png.position_of(|chunk| chunk.length() > 20), Some(3));
+        assert_eq!(png.position_of(|chunk| chunk.length() > 100), None);
+    }
+
+    #[test]
+    fn test_bytes_len_of_type() {
+        let mut png = testing_png();
+        png.append_chunk(chunk_from_strings("miDl", "second").unwrap());
+        assert_eq!(png.bytes_len_of_type("miDl"), 18 + 6);
+        assert_eq!(png.bytes_len_of_type("ruSt"), 0);
+        assert_eq!(png.bytes_len_of_type("bad"), 0);
+    }
+
+    #[test]
+    fn test_data_of_type() {
+        let mut png = testing_png();
+        png.append_chunk(chunk_from_strings("ruSt", "Hello, ").unwrap());
+        png.append_chunk(chunk_from_strings("ruSt", "world!").unwrap());
+        assert_eq!(png.data_of_type("ruSt"), b"Hello, world!".to_vec());
+        assert!(png.data_of_type("teSt").is_empty());
+    }
+
+    #[test]
+    fn test_metadata_round_trip() {
+        let mut png = Png::try_from(&PNG_FILE[..]).unwrap();
+        assert!(png.metadata().unwrap().is_none());
+
+        png.set_metadata(&Value::parse(r#"{"author":"y"}"#).unwrap()).unwrap();
+        png.set_metadata(&Value::parse(r#"{"author":"x"}"#).unwrap()).unwrap();
+        let png = Png::try_from(png.as_bytes().as_ref()).unwrap();
+
+        assert_eq!(png.chunks_with_type_prefix(Png::METADATA_CHUNK_TYPE).len(), 1);
+        assert_eq!(png.last_chunk().unwrap().chunk_type().to_string(), "IEND");
+        assert!(png.validate().is_empty());
+        let metadata = png.metadata().unwrap().unwrap();
+        assert_eq!(metadata.get("author").and_then(Value::as_str), Some("x"));
+    }
+
+    #[test]
+    fn test_extract_to_files() {
+        let dir = std::env::temp_dir().join(format!("pngme-extract-{}", std::process::id()));
+        let mut png = Png::try_from(&PNG_FILE[..]).unwrap();
+        png.append_chunk(chunk_from_strings("ruSt", "first").unwrap());
+        png.append_chunk(chunk_from_strings("ruSt", "second").unwrap());
+
+        let written = png.extract_to_files(&dir, Some("ruSt")).unwrap();
+        let len = png.chunks().len();
+        assert_eq!(written, vec![
+            dir.join(format!("{}_ruSt.bin", len - 2)),
+            dir.join(format!("{}_ruSt.bin", len - 1)),
+        ]);
+        assert_eq!(fs::read(&written[0]).unwrap(), b"first");
+        assert_eq!(fs::read(&written[1]).unwrap(), b"second");
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_text_round_trip() {
+        let mut png = Png::try_from(&PNG_FILE[..]).unwrap();
+        png.append_text("Comment", "Café secret").unwrap();
+        let png = Png::try_from(png.as_bytes().as_ref()).unwrap();
+        assert_eq!(png.last_chunk().unwrap().chunk_type().to_string(), "IEND");
+
+        let chunk = png.chunk_by_type("tEXt").unwrap();
+        assert!(chunk.data().starts_with(b"Comment\0Caf"));
+        assert_eq!(png.text_by_keyword("Comment"), Some("Café secret".to_string()));
+        assert_eq!(png.text_by_keyword("Title"), None);
+    }
+
+    #[test]
+    fn test_text_invalid_input() {
+        let mut png = testing_png();
+        assert!(png.append_text("", "text").is_err());
+        assert!(png.append_text(&"k".repeat(80), "text").is_err());
+        assert!(png.append_text("Comment", "smile ☺").is_err());
+    }
+
+    #[test]
+    #[cfg(feature = "compression")]
+    fn test_ztxt_entries() {
+        let mut png = testing_png();
+        png.append_chunk(Chunk::new_ztxt("Comment", &"long text ".repeat(20)).unwrap());
+        png.append_text("Title", "plain").unwrap();
+        let png = Png::try_from(png.as_bytes().as_ref()).unwrap();
+
+        assert_eq!(png.ztxt_entries().unwrap(), vec![("Comment".to_string(), "long text ".repeat(20))]);
+        assert_eq!(png.text_entries(), vec![("Title".to_string(), "plain".to_string())]);
+    }
+
+    #[test]
+    fn test_append_and_join_split() {
+        let payload: Vec<u8> = (0..1000u32).map(|i| (i % 251) as u8).collect();
+        let mut png = Png::try_from(&PNG_FILE[..]).unwrap();
+        assert_eq!(png.append_split("spLt", &payload, 100).unwrap(), 10);
+        assert_eq!(png.last_chunk().unwrap().chunk_type().to_string(), "IEND");
+        let first = png.position_of(|chunk| *chunk.chunk_type() == *b"spLt").unwrap();
+        assert!(png.chunks()[first..first + 10].iter().all(|chunk| chunk.length() == 108));
+
+        // Reordered pieces still reassemble
+        png.chunks.swap(first, first + 7);
+        let png = Png::try_from(png.as_bytes().as_slice()).unwrap();
+        assert_eq!(png.join_split("spLt").unwrap(), payload);
+
+        let mut empty = testing_png();
+        assert_eq!(empty.append_split("spLt", &[], 100).unwrap(), 1);
+        assert_eq!(empty.join_split("spLt").unwrap(), Vec::<u8>::new());
+        assert!(empty.join_split("ruSt").is_err());
+    }
+
+    #[test]
+    fn test_split_rejects_bad_pieces() {
+        let mut png = testing_png();
+        for piece_size in [0, Png::MAX_PIECE_SIZE + 1] {
+            let err = png.append_split("spLt", b"data", piece_size).unwrap_err();
+            assert!(matches!(err.downcast_ref(), Some(PngError::InvalidPieceSize(size)) if *size == piece_size));
+        }
+
+        png.append_split("spLt", b"split into pieces", 4).unwrap();
+        let second = png.position_of(|chunk| *chunk.chunk_type() == *b"spLt").unwrap() + 1;
+        png.chunks.remove(second);
+        let err = png.join_split("spLt").unwrap_err();
+        assert!(matches!(err.downcast_ref(), Some(PngError::InvalidPayloadPiece(1))));
+        // Restoring piece 1 but losing the last one leaves every piece in sequence with a count that is too high
+        let last = png.chunks.iter().rposition(|chunk| *chunk.chunk_type() == *b"spLt").unwrap();
+        png.chunks.remove(last);
+        png.chunks.insert(second, Chunk::new(ChunkType::from_str("spLt").unwrap(), [0, 0, 0, 1, 0, 0, 0, 5, b' ', b'i', b'n', b't'].to_vec()));
+        let err = png.join_split("spLt").unwrap_err();
+        assert!(matches!(err.downcast_ref(), Some(PngError::InvalidPayloadPiece(4))));
+    }
+
+    #[test]
+    #[cfg(feature = "compression")]
+    fn test_append_ztxt() {
+        let mut png = Png::try_from(&PNG_FILE[..]).unwrap();
+        png.append_ztxt("Description", &"a long description ".repeat(30)).unwrap();
+        png.append_ztxt("Description", "shadowed").unwrap();
+        assert_eq!(png.last_chunk().unwrap().chunk_type().to_string(), "IEND");
+
+        let png = Png::try_from(png.as_bytes().as_slice()).unwrap();
+        assert_eq!(png.ztxt_by_keyword("Description").unwrap(), Some("a long description ".repeat(30)));
+        assert_eq!(png.ztxt_by_keyword("Title").unwrap(), None);
+        assert!(png.chunk_by_type("zTXt").unwrap().length() < 100);
+    }
+
+    #[test]
+    fn test_itxt_entries() {
+        let entry = InternationalText {
+            keyword: "Title".to_string(),
+            language: "ja".to_string(),
+            translated_keyword: "タイトル".to_string(),
+            text: "こんにちは".to_string(),
+            compressed: false,
+        };
+        let mut png = Png::try_from(&PNG_FILE[..]).unwrap();
+        png.append_itxt(&entry).unwrap();
+        png.append_chunk_indexed(Chunk::new_text("Title", "Latin-1 only").unwrap());
+
+        let png = Png::try_from(png.as_bytes().as_slice()).unwrap();
+        assert_eq!(png.itxt_entries().unwrap(), vec![entry]);
+    }
+
+    // Pseudo-random bytes barely compress, so a payload of them spans several chunks
+    #[cfg(feature = "compression")]
+    fn noise(len: usize) -> Vec<u8> {
+        let mut state = 0x2545_f491u32;
+        (0..len).map(|_| {
+            state = state.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+            (state >> 16) as u8
+        }).collect()
+    }
+
+    #[test]
+    #[cfg(feature = "compression")]
+    fn test_hide_and_reveal_payload() {
+        let payload = noise(20_000);
+
+        let mut png = Png::try_from(&PNG_FILE[..]).unwrap();
+        let added = png.hide_payload("hiDe", &payload, false).unwrap();
+        assert!(added > 1);
+        assert_eq!(png.last_chunk().unwrap().chunk_type().to_string(), "IEND");
+
+        let png = Png::try_from(png.as_bytes().as_slice()).unwrap();
+        assert_eq!(png.reveal_payload("hiDe").unwrap(), payload);
+        assert!(png.reveal_payload("ruSt").is_err());
+    }
+
+    #[test]
+    #[cfg(feature = "compression")]
+    fn test_reveal_payload_integrity() {
+        let payload = noise(20_000);
+        let mut png = testing_png();
+        assert_eq!(png.hide_payload("hiDe", &payload, true).unwrap(), 3);
+        assert_eq!(png.reveal_payload("hiDe").unwrap(), payload);
+
+        // Dropping a piece and re-framing the rest as a complete payload must not yield partial data
+        let mut dropped = testing_png();
+        let pieces: Vec<Chunk> = png.chunks().iter().filter(|chunk| *chunk.chunk_type() == *b"hiDe").cloned().collect();
+        for (seq, piece) in [&pieces[0], &pieces[2]].into_iter().enumerate() {
+            let mut data = piece.data().to_vec();
+            data[..4].copy_from_slice(&[0, seq as u8, 0, 2]);
+            dropped.append_chunk(Chunk::new(piece.chunk_type().clone(), data));
+        }
+        assert!(dropped.reveal_payload("hiDe").is_err());
+
+        let mut tampered = pieces[0].data().to_vec();
+        tampered[5] ^= 0xFF;
+        png.replace_data_of_type("hiDe", tampered).unwrap();
+        assert!(matches!(
+            png.reveal_payload("hiDe").unwrap_err().downcast_ref::<PngError>(),
+            Some(PngError::PayloadCrcMismatch(..))
+        ));
+
+        png.retain_chunks(|chunk| chunk.data().get(..2) != Some(&[0, 1]));
+        assert!(png.reveal_payload("hiDe").is_err());
+    }
+
+    #[test]
+    #[cfg(feature = "compression")]
+    fn test_reveal_payload_missing_piece() {
+        let mut png = testing_png();
+        png.hide_payload("hiDe", &vec![7; 100_000], false).unwrap();
+        png.hide_payload("laSt", b"small", false).unwrap();
+        assert_eq!(png.reveal_payload("laSt").unwrap(), b"small");
+
+        let mut data = png.chunk_by_type("hiDe").unwrap().data().to_vec();
+        data[3] += 1;
+        png.replace_data_of_type("hiDe", data).unwrap();
+        assert!(matches!(
+            png.reveal_payload("hiDe").unwrap_err().downcast_ref::<PngError>(),
+            Some(PngError::InvalidPayloadPiece(0))
+        ));
+    }
+
+    #[test]
+    fn test_chunks_with_type_prefix() {
+        let mut png = testing_png();
+        png.append_chunk(chunk_from_strings("tEXt", "Message").unwrap());
+        png.append_chunk(chunk_from_strings("IDAT", "Image").unwrap());
+        let types = |prefix: &str| -> Vec<String> {
+            png.chunks_with_type_prefix(prefix).iter().map(|chunk| chunk.chunk_type().to_string()).collect()
+        };
+        assert_eq!(types("tE"), ["tEXt"]);
+        assert_eq!(types("t"), ["tEXt"]);
+        assert!(!types("t").contains(&"IDAT".to_string()));
+        // Matching is case-sensitive, so the lowercase prefix does not match the uppercase second letter of tEXt
+        assert!(types("te").is_empty());
+        assert_eq!(types("I"), ["IDAT"]);
+    }
+
     #[test]
     fn test_append_chunk() {
         let mut png = testing_png();
@@ -238,6 +1648,18 @@ mod tests {
         assert_eq!(&chunk.data_as_string().unwrap(), "Message");
     }
 
+    #[test]
+    fn test_append_chunk_indexed() {
+        let mut png = Png::try_from(&PNG_FILE[..]).unwrap();
+        let idx = png.append_chunk_indexed(chunk_from_strings("ruSt", "before the end").unwrap());
+        assert_eq!(idx, png.chunks().len() - 2);
+        assert_eq!(png.chunks()[idx].data(), b"before the end");
+        assert!(png.validate().is_empty());
+
+        let mut png = testing_png();
+        assert_eq!(png.append_chunk_indexed(chunk_from_strings("ruSt", "at the end").unwrap()), 3);
+    }
+
     #[test]
     fn test_remove_chunk() {
         let mut png = testing_png();
@@ -247,6 +1669,311 @@ mod tests {
         assert!(chunk.is_none());
     }
 
+    #[test]
+    fn test_swap_remove_chunk() {
+        let mut png = testing_png();
+        let removed = png.swap_remove_chunk(0).unwrap();
+        assert_eq!(removed.chunk_type().to_string(), "FrSt");
+        assert_eq!(png.chunks().len(), 2);
+        assert_eq!(png.chunks()[0].chunk_type().to_string(), "LASt");
+        assert!(png.swap_remove_chunk(2).is_err());
+    }
+
+    #[test]
+    fn test_write_stripped() {
+        let path = std::env::temp_dir().join(format!("pngme-stripped-{}.png", std::process::id()));
+        let mut png = Png::try_from(&PNG_FILE[..]).unwrap();
+        png.append_text("Comment", "private").unwrap();
+        png.append_chunk(chunk_from_strings("ruSt", "hidden").unwrap());
+
+        // The fixture already holds sRGB, gAMA and pHYs
+        png.write_stripped(&path, &[ChunkType::from_str("sRGB").unwrap()]).unwrap();
+        let stripped = Png::try_from(fs::read(&path).unwrap().as_slice()).unwrap();
+        assert!(stripped.chunks().iter().all(|chunk| chunk.chunk_type().is_critical() || *chunk.chunk_type() == *b"sRGB"));
+        assert_eq!(stripped.ancillary_count(), 1);
+        assert_eq!(stripped.critical_count(), png.critical_count());
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_write_atomically_keeps_permissions() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let path = std::env::temp_dir().join(format!("pngme-permissions-{}.png", std::process::id()));
+        fs::write(&path, PNG_FILE).unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o600)).unwrap();
+        Png::try_from(&PNG_FILE[..]).unwrap().write_atomically(&path).unwrap();
+        assert_eq!(fs::metadata(&path).unwrap().permissions().mode() & 0o777, 0o600);
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_write_atomically_keeps_the_file_on_failure() {
+        let dir = std::env::temp_dir().join(format!("pngme-atomic-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("out.png");
+        let png = Png::try_from(&PNG_FILE[..]).unwrap();
+        png.write_atomically(&path).unwrap();
+        assert_eq!(fs::read(&path).unwrap(), png.as_bytes());
+
+        // A directory where the file should go makes the rename fail, the temporary file is cleaned up
+        let blocked = dir.join("blocked.png");
+        fs::create_dir(&blocked).unwrap();
+        fs::write(blocked.join("keep"), b"keep").unwrap();
+        assert!(png.write_atomically(&blocked).is_err());
+        assert_eq!(fs::read(blocked.join("keep")).unwrap(), b"keep");
+        let mut names: Vec<_> = fs::read_dir(&dir).unwrap().map(|entry| entry.unwrap().file_name()).collect();
+        names.sort();
+        assert_eq!(names, ["blocked.png", "out.png"]);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_drain_chunks() {
+        let mut png = testing_png();
+        let drained: Vec<Chunk> = png.drain_chunks().collect();
+        assert!(png.chunks().is_empty());
+        assert_eq!(drained, testing_chunks());
+    }
+
+    #[test]
+    fn test_strip_text() {
+        let mut png = Png::try_from(&PNG_FILE[..]).unwrap();
+        png.append_text("Comment", "remove me").unwrap();
+        png.append_chunk(chunk_from_strings("iTXt", "Title\0\0\0\0\0remove me too").unwrap());
+        png.append_chunk(chunk_from_strings("sRGB", "\0").unwrap());
+        let chunk_count = png.chunks().len();
+
+        assert_eq!(png.strip_text(), 2);
+        assert_eq!(png.chunks().len(), chunk_count - 2);
+        assert!(png.chunk_by_type("sRGB").is_some());
+        assert!(png.chunk_by_type("tEXt").is_none());
+        assert!(png.chunk_by_type("iTXt").is_none());
+    }
+
+    #[test]
+    fn test_truncate_chunks() {
+        let mut chunks = vec![chunk_from_strings("IHDR", "header").unwrap()];
+        chunks.extend((0..8).map(|i| chunk_from_strings("ruSt", &format!("message {}", i)).unwrap()));
+        chunks.push(chunk_from_strings("IEND", "").unwrap());
+        let mut png = Png::from_chunks(chunks);
+
+        png.truncate_chunks(5);
+        let types: Vec<String> = png.chunks().iter().map(|chunk| chunk.chunk_type().to_string()).collect();
+        assert_eq!(types, ["IHDR", "ruSt", "ruSt", "ruSt", "IEND"]);
+        assert_eq!(png.chunks()[3].data(), b"message 2");
+
+        png.truncate_chunks(0);
+        assert_eq!(png.chunks().len(), 2);
+    }
+
+    #[test]
+    fn test_replace_data_of_type() {
+        let mut png = testing_png();
+        let old_crc = png.chunks()[1].crc();
+        png.replace_data_of_type("miDl", b"a new message".to_vec()).unwrap();
+
+        let chunk = &png.chunks()[1];
+        assert_eq!(&chunk.chunk_type().to_string(), "miDl");
+        assert_eq!(chunk.data(), b"a new message");
+        assert_eq!(chunk.length(), 13);
+        assert_ne!(chunk.crc(), old_crc);
+        assert!(Png::try_from(png.as_bytes().as_ref()).is_ok());
+        assert!(png.replace_data_of_type("teSt", Vec::new()).is_err());
+    }
+
+    #[test]
+    fn test_swap_chunks() {
+        let mut png = testing_png();
+        png.swap_chunks(0, 2).unwrap();
+        let types: Vec<String> = png.chunks().iter().map(|c| c.chunk_type().to_string()).collect();
+        assert_eq!(types, vec!["LASt", "miDl", "FrSt"]);
+        assert!(png.swap_chunks(0, 3).is_err());
+    }
+
+    #[test]
+    fn test_validate_valid_png() {
+        let png = Png::try_from(&PNG_FILE[..]).unwrap();
+        assert!(png.validate().is_empty());
+    }
+
+    #[test]
+    fn test_canonicalize_and_recompute_crcs() {
+        // The fixture is IHDR, sRGB, gAMA, pHYs, IDAT, RuSt, IEND
+        let mut png = Png::try_from(&PNG_FILE[..]).unwrap();
+        png.swap_chunks(0, 6).unwrap();
+        png.swap_chunks(1, 4).unwrap();
+        let mut bytes = png.as_bytes();
+        *bytes.last_mut().unwrap() ^= 0xFF;
+
+        let mut png = Png::from_bytes_lenient(&bytes).unwrap();
+        assert!(!png.validate().is_empty());
+        png.canonicalize();
+        png.recompute_crcs();
+
+        assert!(png.validate().is_empty());
+        let types: Vec<String> = png.chunks().iter().map(|chunk| chunk.chunk_type().to_string()).collect();
+        assert_eq!(types, ["IHDR", "gAMA", "sRGB", "pHYs", "IDAT", "RuSt", "IEND"]);
+    }
+
+    #[test]
+    fn test_coalesce_idat() {
+        let mut png = Png::try_from(&PNG_FILE[..]).unwrap();
+        let stream = png.idat_stream();
+        let split = png.remove_chunk("IDAT").unwrap().into_data();
+        let (head, tail) = split.split_at(split.len() / 2);
+        png.chunks.insert(3, Chunk::new(ChunkType::from_str("IDAT").unwrap(), head.to_vec()));
+        png.chunks.insert(4, Chunk::new(ChunkType::from_str("IDAT").unwrap(), tail.to_vec()));
+
+        png.coalesce_idat();
+        assert_eq!(png.chunks().iter().filter(|chunk| *chunk.chunk_type() == *b"IDAT").count(), 1);
+        assert_eq!(png.first_idat_index(), Some(3));
+        assert_eq!(png.idat_stream(), stream);
+    }
+
+    #[test]
+    fn test_sanitize_for_web() {
+        let mut png = Png::try_from(&PNG_FILE[..]).unwrap();
+        png.append_text("Comment", "metadata the browser never shows").unwrap();
+        png.set_trailing_bytes(b"appended".to_vec());
+        let before = png.as_bytes().len();
+
+        png.sanitize_for_web(false);
+        assert!(png.chunks().iter().all(|chunk| chunk.chunk_type().is_critical()));
+        assert!(png.validate().is_empty());
+        assert!(png.trailing_bytes().is_empty());
+        assert!(png.as_bytes().len() < before);
+    }
+
+    #[test]
+    fn test_histogram() {
+        let palette_png = |hist: &[u8]| Png::from_chunks(vec![
+            Chunk::new(ChunkType::from_str("PLTE").unwrap(), vec![0, 0, 0, 255, 255, 255, 255, 0, 0]),
+            Chunk::new(ChunkType::from_str("hIST").unwrap(), hist.to_vec()),
+        ]);
+
+        let png = palette_png(&[0, 10, 1, 0, 0, 0]);
+        assert_eq!(png.histogram().unwrap(), Some(vec![10, 256, 0]));
+
+        let png = palette_png(&[0, 10]);
+        let err = png.histogram().unwrap_err();
+        assert!(matches!(err.downcast_ref::<PngError>(), Some(PngError::InvalidHistogramLength(2, 6))));
+        assert!(png.validate().contains(&"hIST length 2 must be 2 bytes per palette entry, 6 expected".to_string()));
+
+        assert_eq!(testing_png().histogram().unwrap(), None);
+    }
+
+    #[test]
+    fn test_validate_reports_crc_and_ordering() {
+        let mut png = Png::try_from(&PNG_FILE[..]).unwrap();
+        // Move IEND before the custom chunk preceding it
+        let len = png.chunks().len();
+        png.swap_chunks(len - 1, len - 2).unwrap();
+        let mut bytes = png.as_bytes();
+        // Corrupt the CRC of IHDR, which ends at 8 + 12 + 13
+        bytes[32] ^= 0xFF;
+
+        assert!(Png::try_from(bytes.as_ref()).is_err());
+        let png = Png::from_bytes_lenient(bytes.as_ref()).unwrap();
+        let problems = png.validate();
+        assert_eq!(problems.len(), 2);
+        assert!(problems.contains(&"Last chunk must be IEND".to_string()));
+        assert!(problems[1].starts_with("Chunk 0 (IHDR) has CRC"));
+    }
+
+    #[test]
+    fn test_violations_have_offsets_and_severities() {
+        let mut png = Png::try_from(&PNG_FILE[..]).unwrap();
+        let idat = png.first_idat_index().unwrap();
+        png.chunks.insert(idat + 1, Chunk::new(ChunkType::from_str("PLTE").unwrap(), vec![0; 3]));
+        png.set_trailing_bytes(b"after the end".to_vec());
+        let mut bytes = png.as_bytes();
+        // Corrupt the CRC of IHDR, which ends at 8 + 12 + 13
+        bytes[32] ^= 0xFF;
+
+        let png = Png::from_bytes_lenient(&bytes).unwrap();
+        let violations = png.violations();
+        let at = |message: &str| violations.iter().find(|violation| violation.message.starts_with(message)).unwrap();
+        assert_eq!((at("PLTE chunk must come").offset, at("PLTE chunk must come").severity),
+                   (png.byte_offset_of(idat + 1), Severity::Error));
+        assert_eq!(at("Chunk 0 (IHDR) has CRC").offset, Some(29));
+        let trailing = at("13 bytes of trailing data");
+        assert_eq!((trailing.offset, trailing.severity), (Some(bytes.len() - 13), Severity::Warning));
+        assert_eq!(violations.len(), 3);
+        assert_eq!(png.validate().len(), 2);
+    }
+
+    #[test]
+    fn test_lenient_round_trip_preserves_crcs() {
+        let mut bytes = PNG_FILE.to_vec();
+        // Corrupt the CRC of IHDR, which ends at 8 + 12 + 13
+        bytes[32] ^= 0xFF;
+
+        let mut png = Png::from_bytes_lenient(bytes.as_ref()).unwrap();
+        assert_eq!(png.as_bytes(), bytes);
+
+        png.recompute_crcs();
+        assert_eq!(png.as_bytes(), PNG_FILE.to_vec());
+    }
+
+    #[test]
+    fn test_lenient_truncated_chunk() {
+        let mut png = testing_png();
+        png.append_chunk(chunk_from_strings("ruSt", "cut short by a broken download").unwrap());
+        let bytes = png.as_bytes();
+        let bytes = &bytes[..bytes.len() - 10];
+
+        assert!(Png::try_from(bytes).is_err());
+        let png = Png::from_bytes_lenient(bytes).unwrap();
+        let last = png.last_chunk().unwrap();
+        assert!(last.is_truncated());
+        assert_eq!(last.data(), b"cut short by a broken do");
+        assert!(png.validate().contains(&"Chunk 3 (ruSt) is truncated after 24 data bytes".to_string()));
+    }
+
+    #[test]
+    fn test_trailing_bytes_round_trip() {
+        let mut png = Png::try_from(&PNG_FILE[..]).unwrap();
+        assert!(png.trailing_bytes().is_empty());
+        png.set_trailing_bytes(b"hidden after the end".to_vec());
+
+        let bytes = png.as_bytes();
+        assert_eq!(bytes.len(), PNG_FILE.len() + 20);
+        let png = Png::try_from(bytes.as_slice()).unwrap();
+        assert_eq!(png.trailing_bytes(), b"hidden after the end");
+        assert_eq!(png.chunks().len(), Png::try_from(&PNG_FILE[..]).unwrap().chunks().len());
+
+        // Chunks appended after IEND are still parsed as chunks
+        let mut png = Png::try_from(&PNG_FILE[..]).unwrap();
+        png.append_chunk(chunk_from_strings("ruSt", "after").unwrap());
+        let png = Png::try_from(png.as_bytes().as_slice()).unwrap();
+        assert_eq!(png.chunk_by_type("ruSt").unwrap().data(), b"after");
+        assert!(png.trailing_bytes().is_empty());
+    }
+
+    #[test]
+    fn test_from_chunk_stream() {
+        let stream = &PNG_FILE[8..];
+        assert!(Png::try_from(stream).is_err());
+
+        let png = Png::from_chunk_stream(stream, false).unwrap();
+        assert_eq!(png.chunks().len(), Png::try_from(&PNG_FILE[..]).unwrap().chunks().len());
+        assert_eq!(png.as_bytes(), PNG_FILE.to_vec());
+    }
+
+    #[test]
+    fn test_base64_round_trip() {
+        let png = Png::try_from(&PNG_FILE[..]).unwrap();
+        let encoded = png.to_base64();
+        assert!(encoded.starts_with("iVBORw0KGgo"));
+        assert_eq!(Png::from_base64(&encoded).unwrap(), png);
+        assert!(Png::from_base64("not base64!").is_err());
+    }
+
     #[test]
     fn test_png_from_image_file() {
         let png = Png::try_from(&PNG_FILE[..]);
@@ -261,6 +1988,16 @@ mod tests {
         assert_eq!(actual, expected);
     }
 
+    #[test]
+    fn test_write_to() {
+        let mut png = Png::try_from(&PNG_FILE[..]).unwrap();
+        png.set_trailing_bytes(b"trailing".to_vec());
+        let mut out = Vec::new();
+        png.write_to(&mut out).unwrap();
+        assert_eq!(out, png.as_bytes());
+        assert_eq!(&out[..PNG_FILE.len()], &PNG_FILE[..]);
+    }
+
     #[test]
     fn test_png_trait_impls() {
         let chunk_bytes: Vec<u8> = testing_chunks()
@@ -279,6 +2016,34 @@ mod tests {
         let _png_string = format!("{}", png);
     }
 
+    #[test]
+    fn test_try_from_never_panics() {
+        // Cheap xorshift so the test stays deterministic without extra dependencies
+        let mut state: u32 = 0x9E37_79B9;
+        let mut next = || {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            state
+        };
+
+        for _ in 0..500 {
+            let mut bytes = PNG_FILE.to_vec();
+            for _ in 0..(next() % 8 + 1) {
+                let idx = next() as usize % bytes.len();
+                bytes[idx] = next() as u8;
+            }
+            bytes.truncate(next() as usize % (bytes.len() + 1));
+            let _ = Png::try_from(bytes.as_ref());
+        }
+
+        for _ in 0..500 {
+            let len = next() as usize % 64;
+            let bytes: Vec<u8> = (0..len).map(|_| next() as u8).collect();
+            let _ = Png::try_from(bytes.as_ref());
+        }
+    }
+
     // This is the raw bytes for a shrunken version of the `dice.png` image on Wikipedia
     const PNG_FILE: [u8; 4803] = [
         137, 80, 78, 71, 13, 10, 26, 10, 0, 0, 0, 13, 73, 72, 68, 82, 0, 0, 0, 50, 0, 0, 0, 50, 8,
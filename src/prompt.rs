@@ -0,0 +1,38 @@
+use std::io::{self, BufRead, Write};
+
+/// Asks the user to confirm overwriting `path`, anything but "y"/"yes" declines
+pub fn confirm_overwrite<R: BufRead, W: Write>(path: &str, input: &mut R, output: &mut W) -> io::Result<bool> {
+    write!(output, "Overwrite {}? [y/N] ", path)?;
+    output.flush()?;
+
+    let mut answer = String::new();
+    input.read_line(&mut answer)?;
+    Ok(matches!(answer.trim().to_lowercase().as_str(), "y" | "yes"))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+
+    #[test]
+    fn test_confirm_overwrite_yes() {
+        let mut output = Vec::new();
+        let confirmed = confirm_overwrite("image.png", &mut Cursor::new("yes\n"), &mut output).unwrap();
+        assert!(confirmed);
+        assert_eq!(String::from_utf8(output).unwrap(), "Overwrite image.png? [y/N] ");
+    }
+
+    #[test]
+    fn test_confirm_overwrite_no() {
+        let confirmed = confirm_overwrite("image.png", &mut Cursor::new("no\n"), &mut Vec::new()).unwrap();
+        assert!(!confirmed);
+    }
+
+    #[test]
+    fn test_confirm_overwrite_empty_answer() {
+        let confirmed = confirm_overwrite("image.png", &mut Cursor::new(""), &mut Vec::new()).unwrap();
+        assert!(!confirmed);
+    }
+}
@@ -0,0 +1,84 @@
+use std::fmt::{Display, Formatter};
+
+use pngme::chunk::ChunkError;
+use pngme::chunk_type::ChunkTypeError;
+use pngme::ihdr::IhdrError;
+use pngme::json::quote;
+use pngme::png::PngError;
+
+/// Error context naming the file an error happened in, `error_json` reports it instead of the command's file
+#[derive(Debug)]
+pub struct FileContext(pub String);
+
+impl Display for FileContext {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Failed on: {}", self.0)
+    }
+}
+
+/// Renders an error as a single-line JSON object for tools wrapping the CLI,
+/// `file` is only used when the error does not carry a `FileContext` of its own
+pub fn error_json(err: &anyhow::Error, file: Option<&str>) -> String {
+    let mut kind = "Other";
+    let mut offset = None;
+
+    // Walk down to the innermost typed error, it names the actual cause
+    for cause in err.chain() {
+        if let Some(png_err) = cause.downcast_ref::<PngError>() {
+            kind = png_err.kind();
+            offset = offset.or(png_err.offset());
+        } else if let Some(chunk_err) = cause.downcast_ref::<ChunkError>() {
+            kind = chunk_err.kind();
+        } else if let Some(chunk_type_err) = cause.downcast_ref::<ChunkTypeError>() {
+            kind = chunk_type_err.kind();
+        } else if let Some(ihdr_err) = cause.downcast_ref::<IhdrError>() {
+            kind = ihdr_err.kind();
+        } else if cause.downcast_ref::<std::io::Error>().is_some() {
+            kind = "Io";
+        }
+    }
+
+    let message = err.chain().map(|cause| cause.to_string()).collect::<Vec<_>>().join(": ");
+    let file = err.downcast_ref::<FileContext>().map(|context| context.0.as_str()).or(file);
+    let file = file.map_or("null".to_string(), quote);
+    let offset = offset.map_or("null".to_string(), |offset| offset.to_string());
+
+    format!(
+        "{{\"kind\":{},\"message\":{},\"context\":{{\"file\":{},\"offset\":{}}}}}",
+        quote(kind), quote(&message), file, offset
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use pngme::png::Png;
+
+    use super::*;
+
+    #[test]
+    fn test_error_json_chunk_not_found() {
+        let mut png = Png::try_from(&[137, 80, 78, 71, 13, 10, 26, 10][..]).unwrap();
+        let err = png.remove_chunk("ruSt").err().unwrap();
+        let json = error_json(&err, Some("image.png"));
+        assert!(json.contains("\"kind\":\"ChunkNotFound\""));
+        assert!(json.contains("\"file\":\"image.png\""));
+        assert!(json.contains("\"offset\":null"));
+    }
+
+    #[test]
+    fn test_error_json_names_file_from_context() {
+        let err = Png::try_from(&[0u8; 4][..]).err().unwrap().context(FileContext("second.png".to_string()));
+        let json = error_json(&err, Some("first.png"));
+        assert!(json.contains("\"kind\":\"FileTooSmall\""));
+        assert!(json.contains("\"file\":\"second.png\""));
+    }
+
+    #[test]
+    fn test_error_json_carries_offset() {
+        let bytes = [137, 80, 78, 71, 13, 10, 26, 10, 0, 0, 0, 1, 73, 69, 78, 68, 0, 1, 2, 3, 4];
+        let err = Png::try_from(&bytes[..]).err().unwrap();
+        let json = error_json(&err, None);
+        assert!(json.contains("\"kind\":\"CrcMismatch\""));
+        assert!(json.contains("\"offset\":8"));
+    }
+}
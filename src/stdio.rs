@@ -0,0 +1,65 @@
+use std::fmt::Display;
+use std::fs;
+use std::io::{self, Read, Write};
+
+/// File path standing for stdin when read and stdout when written
+pub const STDIO_PATH: &str = "-";
+
+pub fn is_stdio(path: &str) -> bool {
+    path == STDIO_PATH
+}
+
+/// Every byte of the file at `path`, or of stdin for `-`
+pub fn read_input(path: &str) -> io::Result<Vec<u8>> {
+    if !is_stdio(path) {
+        return fs::read(path);
+    }
+    let mut content = Vec::new();
+    io::stdin().lock().read_to_end(&mut content)?;
+    Ok(content)
+}
+
+/// Writes `bytes` to the file at `path`, or to stdout for `-`
+pub fn write_output(path: &str, bytes: &[u8]) -> io::Result<()> {
+    if !is_stdio(path) {
+        return fs::write(path, bytes);
+    }
+    let mut stdout = io::stdout().lock();
+    stdout.write_all(bytes)?;
+    stdout.flush()
+}
+
+/// Where status lines go, stderr when the output file is stdout so the written bytes stay intact
+pub fn status_out(output_file: &str) -> Box<dyn Write> {
+    if is_stdio(output_file) {
+        Box::new(io::stderr())
+    } else {
+        Box::new(io::stdout())
+    }
+}
+
+/// Prints a status line on stdout, or on stderr when the output file is stdout so the written bytes stay intact
+pub fn status<T: Display>(output_file: &str, message: T) {
+    if is_stdio(output_file) {
+        eprintln!("{}", message);
+    } else {
+        println!("{}", message);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_and_write_files() {
+        let path = std::env::temp_dir().join(format!("pngme-stdio-{}", std::process::id()));
+        let path = path.to_str().unwrap();
+        write_output(path, &[0x89, 0x50, 0xFF]).unwrap();
+        assert_eq!(read_input(path).unwrap(), [0x89, 0x50, 0xFF]);
+        fs::remove_file(path).unwrap();
+
+        assert!(is_stdio("-"));
+        assert!(!is_stdio("./-"));
+    }
+}
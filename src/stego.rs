@@ -0,0 +1,224 @@
+//! Hides data in the least significant bit of every image sample instead of in a chunk of its own.
+//!
+//! The payload is stored as its big-endian u32 length followed by its bytes, most significant bit first,
+//! one bit per sample byte in scanline order. Only 8-bit, non-interlaced grayscale and truecolor images,
+//! with or without alpha, are supported: flipping the low bit of a palette index can change a pixel entirely.
+
+use anyhow::bail;
+use thiserror::Error;
+
+use crate::png::Png;
+use crate::zlib;
+
+#[derive(Error, Debug)]
+pub enum StegoError {
+    #[error("LSB mode needs an 8-bit non-interlaced grayscale or truecolor image, got bit depth {0}, color type {1} and interlace method {2}")]
+    UnsupportedImage(u8, u8, u8),
+    #[error("Image data holds {0} bytes but IHDR implies {1}")]
+    InvalidDataLength(usize, usize),
+    #[error("Invalid scanline filter type {0}")]
+    InvalidFilter(u8),
+    #[error("Payload of {0} bytes does not fit, the image holds at most {1}")]
+    PayloadTooLarge(usize, usize),
+    #[error("Stored payload length {0} exceeds the image capacity of {1} bytes")]
+    InvalidPayloadLength(usize, usize),
+    #[error("Image of {0}x{1} pixels holds more samples than can be addressed")]
+    ImageTooLarge(u32, u32),
+}
+
+// Bytes of the length prefix stored before the payload
+const LENGTH_LEN: usize = 4;
+
+// Row count, bytes per pixel and sample counts of an image LSB mode can handle
+struct Geometry {
+    height: usize,
+    channels: usize,
+    stride: usize,
+    samples: usize,
+    filtered: usize,
+}
+
+impl Geometry {
+    fn of(png: &Png) -> anyhow::Result<Geometry> {
+        let header = png.header()?;
+        if header.bit_depth != 8 || header.color_type == 3 || header.is_interlaced() {
+            bail!(StegoError::UnsupportedImage(header.bit_depth, header.color_type, header.interlace_method));
+        }
+        // The dimensions come straight from the file, so every size derived from them is checked
+        let (height, channels) = (header.height as usize, header.channels());
+        let sizes = (header.width as usize).checked_mul(channels).and_then(|stride| {
+            let samples = stride.checked_mul(height)?;
+            Some((stride, samples, samples.checked_add(height)?))
+        });
+        let Some((stride, samples, filtered)) = sizes else {
+            bail!(StegoError::ImageTooLarge(header.width, header.height));
+        };
+        Ok(Geometry { height, channels, stride, samples, filtered })
+    }
+
+    // The image data is never larger than a filter byte per row on top of the samples, more means a crafted stream
+    fn inflate(&self, png: &Png) -> anyhow::Result<Vec<u8>> {
+        zlib::decompress_limited(&png.idat_stream(), self.filtered)
+    }
+}
+
+/// Largest payload in bytes `embed` can store in the image
+pub fn capacity(png: &Png) -> anyhow::Result<usize> {
+    Ok((Geometry::of(png)?.samples / 8).saturating_sub(LENGTH_LEN))
+}
+
+/// Overwrites the low bit of the image samples with `payload`, rewriting the image data as a single IDAT chunk
+pub fn embed(png: &mut Png, payload: &[u8]) -> anyhow::Result<()> {
+    let geometry = Geometry::of(png)?;
+    let capacity = (geometry.samples / 8).saturating_sub(LENGTH_LEN);
+    let length = u32::try_from(payload.len()).ok().filter(|_| payload.len() <= capacity)
+        .ok_or(StegoError::PayloadTooLarge(payload.len(), capacity))?;
+
+    let mut samples = unfilter(&geometry.inflate(png)?, &geometry)?;
+    let bits = length.to_be_bytes().into_iter().chain(payload.iter().copied())
+        .flat_map(|byte| (0..8).rev().map(move |bit| (byte >> bit) & 1));
+    for (sample, bit) in samples.iter_mut().zip(bits) {
+        *sample = (*sample & !1) | bit;
+    }
+
+    // Every row is stored unfiltered, filtering against neighbours would not survive the changed low bits anyway
+    let mut filtered = Vec::with_capacity(geometry.filtered);
+    for row in samples.chunks(geometry.stride.max(1)).take(geometry.height) {
+        filtered.push(0);
+        filtered.extend_from_slice(row);
+    }
+    png.coalesce_idat();
+    png.replace_data_of_type("IDAT", zlib::compress(&filtered))
+}
+
+/// Reads back the payload stored by `embed`
+pub fn extract(png: &Png) -> anyhow::Result<Vec<u8>> {
+    let geometry = Geometry::of(png)?;
+    let capacity = (geometry.samples / 8).saturating_sub(LENGTH_LEN);
+    let samples = unfilter(&geometry.inflate(png)?, &geometry)?;
+    let mut bytes = samples.chunks_exact(8)
+        .map(|bits| bits.iter().fold(0u8, |byte, sample| (byte << 1) | (sample & 1)));
+
+    let length = u32::from_be_bytes([(); LENGTH_LEN].map(|_| bytes.next().unwrap_or(0))) as usize;
+    if length > capacity {
+        bail!(StegoError::InvalidPayloadLength(length, capacity));
+    }
+    Ok(bytes.take(length).collect())
+}
+
+// Reverses the per-row filters, returning the samples without the filter type bytes
+fn unfilter(data: &[u8], geometry: &Geometry) -> anyhow::Result<Vec<u8>> {
+    let (stride, bpp) = (geometry.stride, geometry.channels);
+    let expected = if stride == 0 { 0 } else { geometry.filtered };
+    if data.len() != expected {
+        bail!(StegoError::InvalidDataLength(data.len(), expected));
+    }
+
+    let mut samples = vec![0u8; geometry.samples];
+    for (y, row) in data.chunks_exact(stride.saturating_add(1)).enumerate() {
+        let (filter, row) = (row[0], &row[1..]);
+        let (before, current) = samples.split_at_mut(y * stride);
+        let above = before.get(before.len().saturating_sub(stride)..).filter(|_| y > 0);
+        let current = &mut current[..stride];
+        for x in 0..stride {
+            let a = if x >= bpp { current[x - bpp] } else { 0 };
+            let b = above.map_or(0, |above| above[x]);
+            let c = if x >= bpp { above.map_or(0, |above| above[x - bpp]) } else { 0 };
+            let predicted = match filter {
+                0 => 0,
+                1 => a,
+                2 => b,
+                3 => ((a as u16 + b as u16) / 2) as u8,
+                4 => paeth(a, b, c),
+                _ => bail!(StegoError::InvalidFilter(filter)),
+            };
+            current[x] = row[x].wrapping_add(predicted);
+        }
+    }
+    Ok(samples)
+}
+
+fn paeth(a: u8, b: u8, c: u8) -> u8 {
+    let p = a as i16 + b as i16 - c as i16;
+    let (pa, pb, pc) = ((p - a as i16).abs(), (p - b as i16).abs(), (p - c as i16).abs());
+    if pa <= pb && pa <= pc {
+        a
+    } else if pb <= pc {
+        b
+    } else {
+        c
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chunk::Chunk;
+    use crate::chunk_type::ChunkType;
+    use crate::ihdr::IhdrError;
+    use std::str::FromStr;
+
+    const DICE: &[u8] = include_bytes!("../fuzz/corpus/png_try_from/dice.png");
+
+    fn samples(png: &Png) -> Vec<u8> {
+        let geometry = Geometry::of(png).unwrap();
+        unfilter(&geometry.inflate(png).unwrap(), &geometry).unwrap()
+    }
+
+    #[test]
+    fn test_embed_and_extract() {
+        let mut png = Png::try_from(DICE).unwrap();
+        let original = samples(&png);
+        assert_eq!(capacity(&png).unwrap(), 50 * 50 * 4 / 8 - 4);
+
+        embed(&mut png, b"hidden in plain sight").unwrap();
+        let png = Png::try_from(png.as_bytes().as_slice()).unwrap();
+        assert_eq!(extract(&png).unwrap(), b"hidden in plain sight");
+        assert!(png.validate_ihdr_matches_data().unwrap().is_none());
+
+        // Only the low bits changed, so the image looks the same
+        let changed = samples(&png);
+        assert!(original.iter().zip(&changed).all(|(before, after)| before >> 1 == after >> 1));
+        assert_ne!(original, changed);
+    }
+
+    #[test]
+    fn test_payload_must_fit() {
+        let mut png = Png::try_from(DICE).unwrap();
+        let capacity = capacity(&png).unwrap();
+        assert!(embed(&mut png, &vec![0xA5; capacity]).is_ok());
+        assert_eq!(extract(&png).unwrap(), vec![0xA5; capacity]);
+
+        let err = embed(&mut png, &vec![0; capacity + 1]).unwrap_err();
+        assert!(matches!(err.downcast_ref(), Some(StegoError::PayloadTooLarge(len, max)) if *len == capacity + 1 && *max == capacity));
+    }
+
+    #[test]
+    fn test_rejects_palette_images() {
+        let mut png = Png::try_from(DICE).unwrap();
+        png.replace_data_of_type("IHDR", vec![0, 0, 0, 50, 0, 0, 0, 50, 8, 3, 0, 0, 0]).unwrap();
+        let err = embed(&mut png, b"hi").unwrap_err();
+        assert!(matches!(err.downcast_ref(), Some(StegoError::UnsupportedImage(8, 3, 0))));
+
+        let png = Png::from_chunks(vec![Chunk::new(ChunkType::from_str("IHDR").unwrap(), vec![0; 3])]);
+        assert!(matches!(extract(&png).unwrap_err().downcast_ref(), Some(IhdrError::InvalidLength(3))));
+    }
+
+    #[test]
+    fn test_rejects_unaddressable_images() {
+        let mut png = Png::try_from(DICE).unwrap();
+        png.replace_data_of_type("IHDR", vec![0x7F, 0xFF, 0xFF, 0xFF, 0x7F, 0xFF, 0xFF, 0xFF, 8, 6, 0, 0, 0]).unwrap();
+        // The sample count only fits a 64-bit usize, where the real image data is then too short instead
+        let (embedded, extracted) = (embed(&mut png, b"hi").unwrap_err(), extract(&png).unwrap_err());
+        if cfg!(target_pointer_width = "64") {
+            assert_eq!(capacity(&png).unwrap() as u64, 0x7FFFFFFF * 0x7FFFFFFF * 4 / 8 - 4);
+            for err in [embedded, extracted] {
+                assert!(matches!(err.downcast_ref(), Some(StegoError::InvalidDataLength(_, _))));
+            }
+        } else {
+            for err in [capacity(&png).unwrap_err(), embedded, extracted] {
+                assert!(matches!(err.downcast_ref(), Some(StegoError::ImageTooLarge(0x7FFFFFFF, 0x7FFFFFFF))));
+            }
+        }
+    }
+}
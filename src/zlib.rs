@@ -0,0 +1,474 @@
+//! Minimal zlib (RFC 1950) / deflate (RFC 1951) codec used for compressed PNG chunks.
+//!
+//! Compression emits a single fixed-Huffman block with LZ77 matching, decompression
+//! handles stored, fixed and dynamic blocks.
+
+use anyhow::bail;
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum ZlibError {
+    #[error("Zlib stream is truncated")]
+    UnexpectedEnd,
+    #[error("Invalid zlib header")]
+    InvalidHeader,
+    #[error("Invalid deflate block type {0}")]
+    InvalidBlockType(u32),
+    #[error("Stored block length does not match its complement")]
+    InvalidStoredLength,
+    #[error("Invalid Huffman code in deflate stream")]
+    InvalidCode,
+    #[error("Back reference distance {0} reaches before the start of the output")]
+    InvalidDistance(usize),
+    #[error("Adler-32 mismatch, expected {0} but computed {1}")]
+    ChecksumMismatch(u32, u32),
+    #[error("Decompressed data exceeds the limit of {0} bytes")]
+    OutputTooLarge(usize),
+}
+
+const LENGTH_BASE: [u16; 29] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131,
+    163, 195, 227, 258,
+];
+const LENGTH_EXTRA: [u8; 29] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0,
+];
+const DIST_BASE: [u16; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537, 2049,
+    3073, 4097, 6145, 8193, 12289, 16385, 24577,
+];
+const DIST_EXTRA: [u8; 30] = [
+    0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13, 13,
+];
+// Order in which code length code lengths are stored in a dynamic block header
+const CODE_LENGTH_ORDER: [usize; 19] = [16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15];
+
+const WINDOW_SIZE: usize = 32768;
+const MIN_MATCH: usize = 3;
+const MAX_MATCH: usize = 258;
+const MAX_CHAIN: usize = 64;
+
+/// Output size `decompress` stops at, a few kilobytes of crafted input could otherwise inflate to gigabytes
+pub const DECOMPRESS_LIMIT: usize = 256 << 20;
+
+pub fn adler32(data: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    for chunk in data.chunks(5552) {
+        for byte in chunk {
+            a += *byte as u32;
+            b += a;
+        }
+        a %= 65521;
+        b %= 65521;
+    }
+    (b << 16) | a
+}
+
+/// Compresses `data` into a zlib stream
+pub fn compress(data: &[u8]) -> Vec<u8> {
+    let mut writer = BitWriter::default();
+    // CMF: deflate with a 32K window, FLG: default level, check bits make the pair divisible by 31
+    writer.bytes.extend_from_slice(&[0x78, 0x9C]);
+    // Final block, fixed Huffman codes
+    writer.write_bits(1, 1);
+    writer.write_bits(1, 2);
+
+    let mut head = vec![usize::MAX; 1 << 15];
+    let mut prev = vec![usize::MAX; data.len()];
+    let hash = |pos: usize| {
+        ((data[pos] as usize) << 10 ^ (data[pos + 1] as usize) << 5 ^ data[pos + 2] as usize) & 0x7FFF
+    };
+
+    let mut pos = 0;
+    while pos < data.len() {
+        let mut best_len = 0;
+        let mut best_dist = 0;
+        if pos + MIN_MATCH <= data.len() {
+            let h = hash(pos);
+            let mut candidate = head[h];
+            let mut chain = 0;
+            while candidate != usize::MAX && pos - candidate <= WINDOW_SIZE && chain < MAX_CHAIN {
+                let max = (data.len() - pos).min(MAX_MATCH);
+                let len = (0..max).take_while(|i| data[candidate + i] == data[pos + i]).count();
+                if len > best_len {
+                    best_len = len;
+                    best_dist = pos - candidate;
+                    if len == max {
+                        break;
+                    }
+                }
+                candidate = prev[candidate];
+                chain += 1;
+            }
+        }
+
+        let advance = if best_len >= MIN_MATCH {
+            writer.write_length(best_len);
+            writer.write_distance(best_dist);
+            best_len
+        } else {
+            writer.write_literal(data[pos] as u16);
+            1
+        };
+
+        let end = (pos + advance).min((data.len() + 1).saturating_sub(MIN_MATCH));
+        for (p, link) in prev.iter_mut().enumerate().take(end).skip(pos) {
+            let h = hash(p);
+            *link = head[h];
+            head[h] = p;
+        }
+        pos += advance;
+    }
+
+    writer.write_literal(256);
+    let mut out = writer.finish();
+    out.extend_from_slice(&adler32(data).to_be_bytes());
+    out
+}
+
+/// Decompresses a complete zlib stream of at most `DECOMPRESS_LIMIT` bytes, verifying its Adler-32 checksum
+pub fn decompress(data: &[u8]) -> anyhow::Result<Vec<u8>> {
+    decompress_limited(data, DECOMPRESS_LIMIT)
+}
+
+/// Like `decompress`, but fails with `ZlibError::OutputTooLarge` as soon as the output would exceed `limit` bytes
+pub fn decompress_limited(data: &[u8], limit: usize) -> anyhow::Result<Vec<u8>> {
+    if data.len() < 6 {
+        bail!(ZlibError::UnexpectedEnd);
+    }
+    let (cmf, flg) = (data[0], data[1]);
+    // Compression method 8 (deflate), no preset dictionary, valid check bits
+    if cmf & 0x0F != 8 || cmf >> 4 > 7 || flg & 0x20 != 0 || !((cmf as u16) << 8 | flg as u16).is_multiple_of(31) {
+        bail!(ZlibError::InvalidHeader);
+    }
+
+    let mut reader = BitReader { data: &data[2..], pos: 0, bit: 0 };
+    let out = inflate(&mut reader, limit)?;
+
+    let trailer_start = 2 + reader.byte_position();
+    let trailer = data.get(trailer_start..trailer_start + 4).ok_or(ZlibError::UnexpectedEnd)?;
+    let expected = u32::from_be_bytes([trailer[0], trailer[1], trailer[2], trailer[3]]);
+    let actual = adler32(&out);
+    if expected != actual {
+        bail!(ZlibError::ChecksumMismatch(expected, actual));
+    }
+    Ok(out)
+}
+
+fn inflate(reader: &mut BitReader, limit: usize) -> anyhow::Result<Vec<u8>> {
+    let mut out = Vec::new();
+    loop {
+        let last = reader.bits(1)? == 1;
+        match reader.bits(2)? {
+            0 => {
+                reader.align();
+                let len = reader.bits(16)? as u16;
+                let nlen = reader.bits(16)? as u16;
+                if len != !nlen {
+                    bail!(ZlibError::InvalidStoredLength);
+                }
+                if out.len() + len as usize > limit {
+                    bail!(ZlibError::OutputTooLarge(limit));
+                }
+                for _ in 0..len {
+                    out.push(reader.bits(8)? as u8);
+                }
+            }
+            1 => {
+                let mut lengths = [0u8; 288];
+                lengths[..144].fill(8);
+                lengths[144..256].fill(9);
+                lengths[256..280].fill(7);
+                lengths[280..].fill(8);
+                let literals = Huffman::new(&lengths);
+                let distances = Huffman::new(&[5; 30]);
+                inflate_block(reader, &mut out, limit, &literals, &distances)?;
+            }
+            2 => {
+                let (literals, distances) = read_dynamic_tables(reader)?;
+                inflate_block(reader, &mut out, limit, &literals, &distances)?;
+            }
+            other => bail!(ZlibError::InvalidBlockType(other)),
+        }
+        if last {
+            return Ok(out);
+        }
+    }
+}
+
+fn read_dynamic_tables(reader: &mut BitReader) -> anyhow::Result<(Huffman, Huffman)> {
+    let literal_count = reader.bits(5)? as usize + 257;
+    let distance_count = reader.bits(5)? as usize + 1;
+    let code_length_count = reader.bits(4)? as usize + 4;
+
+    let mut code_lengths = [0u8; 19];
+    for idx in CODE_LENGTH_ORDER.iter().take(code_length_count) {
+        code_lengths[*idx] = reader.bits(3)? as u8;
+    }
+    let code_length_codes = Huffman::new(&code_lengths);
+
+    let mut lengths = Vec::with_capacity(literal_count + distance_count);
+    while lengths.len() < literal_count + distance_count {
+        let symbol = code_length_codes.decode(reader)?;
+        let (value, repeat) = match symbol {
+            0..=15 => (symbol as u8, 1),
+            16 => (*lengths.last().ok_or(ZlibError::InvalidCode)?, 3 + reader.bits(2)? as usize),
+            17 => (0, 3 + reader.bits(3)? as usize),
+            18 => (0, 11 + reader.bits(7)? as usize),
+            _ => bail!(ZlibError::InvalidCode),
+        };
+        lengths.extend(std::iter::repeat_n(value, repeat));
+    }
+    if lengths.len() != literal_count + distance_count {
+        bail!(ZlibError::InvalidCode);
+    }
+
+    let (literal_lengths, distance_lengths) = lengths.split_at(literal_count);
+    Ok((Huffman::new(literal_lengths), Huffman::new(distance_lengths)))
+}
+
+fn inflate_block(reader: &mut BitReader, out: &mut Vec<u8>, limit: usize, literals: &Huffman, distances: &Huffman) -> anyhow::Result<()> {
+    loop {
+        let symbol = literals.decode(reader)? as usize;
+        match symbol {
+            0..=255 if out.len() >= limit => bail!(ZlibError::OutputTooLarge(limit)),
+            0..=255 => out.push(symbol as u8),
+            256 => return Ok(()),
+            257..=285 => {
+                let idx = symbol - 257;
+                let len = LENGTH_BASE[idx] as usize + reader.bits(LENGTH_EXTRA[idx] as u32)? as usize;
+                let dist_symbol = distances.decode(reader)? as usize;
+                if dist_symbol >= 30 {
+                    bail!(ZlibError::InvalidCode);
+                }
+                let dist = DIST_BASE[dist_symbol] as usize + reader.bits(DIST_EXTRA[dist_symbol] as u32)? as usize;
+                if dist > out.len() {
+                    bail!(ZlibError::InvalidDistance(dist));
+                }
+                if out.len() + len > limit {
+                    bail!(ZlibError::OutputTooLarge(limit));
+                }
+                let start = out.len() - dist;
+                for i in 0..len {
+                    out.push(out[start + i]);
+                }
+            }
+            _ => bail!(ZlibError::InvalidCode),
+        }
+    }
+}
+
+/// Canonical Huffman decoding table, as described in RFC 1951 section 3.2.2
+struct Huffman {
+    counts: [u16; 16],
+    symbols: Vec<u16>,
+}
+
+impl Huffman {
+    fn new(lengths: &[u8]) -> Huffman {
+        let mut counts = [0u16; 16];
+        for len in lengths {
+            counts[*len as usize] += 1;
+        }
+        counts[0] = 0;
+
+        let mut offsets = [0u16; 16];
+        for len in 1..16 {
+            offsets[len] = offsets[len - 1] + counts[len - 1];
+        }
+        let mut symbols = vec![0u16; lengths.len()];
+        for (symbol, len) in lengths.iter().enumerate() {
+            if *len != 0 {
+                symbols[offsets[*len as usize] as usize] = symbol as u16;
+                offsets[*len as usize] += 1;
+            }
+        }
+        Huffman { counts, symbols }
+    }
+
+    fn decode(&self, reader: &mut BitReader) -> anyhow::Result<u16> {
+        let (mut code, mut first, mut index) = (0i32, 0i32, 0i32);
+        for len in 1..16 {
+            code |= reader.bits(1)? as i32;
+            let count = self.counts[len] as i32;
+            if code - count < first {
+                return Ok(self.symbols[(index + code - first) as usize]);
+            }
+            index += count;
+            first += count;
+            first <<= 1;
+            code <<= 1;
+        }
+        bail!(ZlibError::InvalidCode)
+    }
+}
+
+struct BitReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+    bit: u32,
+}
+
+impl BitReader<'_> {
+    fn bits(&mut self, count: u32) -> anyhow::Result<u32> {
+        let mut value = 0;
+        for i in 0..count {
+            let byte = *self.data.get(self.pos).ok_or(ZlibError::UnexpectedEnd)?;
+            value |= (((byte >> self.bit) & 1) as u32) << i;
+            self.bit += 1;
+            if self.bit == 8 {
+                self.bit = 0;
+                self.pos += 1;
+            }
+        }
+        Ok(value)
+    }
+
+    fn align(&mut self) {
+        if self.bit != 0 {
+            self.bit = 0;
+            self.pos += 1;
+        }
+    }
+
+    fn byte_position(&self) -> usize {
+        self.pos + usize::from(self.bit != 0)
+    }
+}
+
+#[derive(Default)]
+struct BitWriter {
+    bytes: Vec<u8>,
+    current: u32,
+    filled: u32,
+}
+
+impl BitWriter {
+    fn write_bits(&mut self, value: u32, count: u32) {
+        for i in 0..count {
+            self.current |= ((value >> i) & 1) << self.filled;
+            self.filled += 1;
+            if self.filled == 8 {
+                self.bytes.push(self.current as u8);
+                self.current = 0;
+                self.filled = 0;
+            }
+        }
+    }
+
+    // Huffman codes are packed starting from their most significant bit
+    fn write_code(&mut self, code: u32, len: u32) {
+        let reversed = (0..len).fold(0, |acc, i| acc | ((code >> i) & 1) << (len - 1 - i));
+        self.write_bits(reversed, len);
+    }
+
+    fn write_literal(&mut self, symbol: u16) {
+        let symbol = symbol as u32;
+        match symbol {
+            0..=143 => self.write_code(0x30 + symbol, 8),
+            144..=255 => self.write_code(0x190 + symbol - 144, 9),
+            256..=279 => self.write_code(symbol - 256, 7),
+            _ => self.write_code(0xC0 + symbol - 280, 8),
+        }
+    }
+
+    fn write_length(&mut self, len: usize) {
+        let idx = LENGTH_BASE.iter().rposition(|base| *base as usize <= len).unwrap();
+        self.write_literal(257 + idx as u16);
+        self.write_bits((len - LENGTH_BASE[idx] as usize) as u32, LENGTH_EXTRA[idx] as u32);
+    }
+
+    fn write_distance(&mut self, dist: usize) {
+        let idx = DIST_BASE.iter().rposition(|base| *base as usize <= dist).unwrap();
+        self.write_code(idx as u32, 5);
+        self.write_bits((dist - DIST_BASE[idx] as usize) as u32, DIST_EXTRA[idx] as u32);
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        if self.filled > 0 {
+            self.bytes.push(self.current as u8);
+        }
+        self.bytes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_adler32() {
+        assert_eq!(adler32(b"Wikipedia"), 0x11E60398);
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let inputs: [&[u8]; 4] = [
+            b"",
+            b"a",
+            b"This is where your secret message will be! This is where your secret message will be!",
+            &[0u8; 100_000],
+        ];
+        for input in inputs {
+            let compressed = compress(input);
+            assert_eq!(decompress(&compressed).unwrap(), input);
+        }
+    }
+
+    #[test]
+    fn test_compression_shrinks_repetitive_data() {
+        let input = b"pngme ".repeat(1000);
+        assert!(compress(&input).len() < input.len() / 10);
+    }
+
+    #[test]
+    fn test_decompress_stored_block() {
+        // Produced by zlib with level 0
+        let stream = [0x78, 0x01, 0x01, 0x05, 0x00, 0xFA, 0xFF, b'h', b'e', b'l', b'l', b'o', 0x06, 0x2C, 0x02, 0x15];
+        assert_eq!(decompress(&stream).unwrap(), b"hello");
+    }
+
+    #[test]
+    fn test_decompress_fixed_block() {
+        // The scanline of a 1x1 RGBA image, compressed by zlib with level 9
+        let stream = [0x78, 0xDA, 0x63, 0xF8, 0xCF, 0xC0, 0xF0, 0x1F, 0x00, 0x05, 0x00, 0x01, 0xFF];
+        assert_eq!(decompress(&stream).unwrap(), [0, 255, 0, 0, 255]);
+    }
+
+    #[test]
+    fn test_decompress_dynamic_block() {
+        // Produced by zlib with level 9, which picks a dynamic block for this skewed alphabet
+        #[rustfmt::skip]
+        let stream = [
+            120, 218, 21, 140, 193, 17, 0, 64, 12, 1, 91, 73, 107, 228, 72, 255, 29, 28, 51, 121, 32,
+            11, 116, 6, 33, 63, 105, 15, 16, 102, 87, 54, 244, 232, 139, 37, 18, 10, 155, 75, 136, 114,
+            229, 35, 136, 157, 152, 116, 9, 211, 233, 107, 242, 163, 170, 74, 92, 150, 16, 235, 18, 237,
+            101, 96, 150, 234, 206, 7, 208, 123, 45, 158,
+        ];
+        let expected = "aegfabaefdeecgaaea cceffaedbfgaeabaaaeeaceacfaeafdeeabaeafdbac eeaaefbafbfeece \
+                        eabbeeeceaeafgcceabeefefbadeeafae cbefaea";
+        assert_eq!(decompress(&stream).unwrap(), expected.as_bytes());
+    }
+
+    #[test]
+    fn test_decompress_limited() {
+        let bomb = compress(&[0u8; 100_000]);
+        assert_eq!(decompress_limited(&bomb, 100_000).unwrap().len(), 100_000);
+        let err = decompress_limited(&bomb, 99_999).unwrap_err();
+        assert!(matches!(err.downcast_ref(), Some(ZlibError::OutputTooLarge(99_999))));
+
+        let stored = [0x78, 0x01, 0x01, 0x05, 0x00, 0xFA, 0xFF, b'h', b'e', b'l', b'l', b'o', 0x06, 0x2C, 0x02, 0x15];
+        assert!(decompress_limited(&stored, 4).is_err());
+        assert!(decompress_limited(&compress(b"abc"), 2).is_err());
+    }
+
+    #[test]
+    fn test_decompress_rejects_corruption() {
+        let mut compressed = compress(b"hello hello hello");
+        let last = compressed.len() - 1;
+        compressed[last] ^= 1;
+        assert!(decompress(&compressed).is_err());
+        assert!(decompress(&[0x78, 0x9C]).is_err());
+        assert!(decompress(&[0x00, 0x00, 0, 0, 0, 0]).is_err());
+    }
+}